@@ -1,35 +1,249 @@
-use crate::profile_loader::{Browsers, Installation, Profile};
+use crate::profile_loader::{Browsers, Container, Installation, LaunchStyle, Profile};
+use crate::router::handler::SchemeHandler;
+use crate::router::script::ScriptConfig;
+use crate::router::Rule;
+use crate::ui::DialogConfig;
+use crate::uri::homograph::HomographConfig;
+use crate::uri::policy::SchemePolicy;
+use crate::uri::rewrite::Rewrite;
+use crate::uri::tracking::TrackingConfig;
+use crate::uri::unwrap::UnwrapConfig;
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "linux")]
+use std::os::unix::process::CommandExt;
+#[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use serde::{Serialize, Deserialize};
 
 pub struct CommandArguments {
-    pub uri: String,
+    /// Opened together in a single command
+    pub uris: Vec<String>,
     pub browser_type: Browsers,
     pub executable: PathBuf,
+    pub launch: LaunchStyle,
     pub profile: Profile,
+    /// The name of the container inside the profile to open the URIs in
+    pub container: Option<String>,
 }
 
 impl CommandArguments {
     pub fn create_command(&self) -> Command {
-        let mut command = Command::new(&self.executable);
-        self.browser_type
-            .add_args_to_command(&mut command, &self.profile.profile_path, &self.uri);
-        #[cfg(target_os = "windows")]
-        command.creation_flags(0x210);
-        #[cfg(target_os = "linux")]
-        command.process_group(0);
+        let mut command = self.launch.command(&self.executable);
+        let uris: Vec<String> = match &self.container {
+            Some(container) => self
+                .uris
+                .iter()
+                .map(|uri| Container::wrap_uri(container, uri))
+                .collect(),
+            None => self.uris.clone(),
+        };
+        self.profile
+            .add_args_to_command(&mut command, &self.browser_type, &self.launch, &uris);
+        detach(&mut command);
         command
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Merges arguments launching the same executable with the same profile, so that their URIs
+/// are opened by a single command
+#[must_use]
+pub fn merge_by_target(command_args: Vec<CommandArguments>) -> Vec<CommandArguments> {
+    let mut merged: Vec<CommandArguments> = Vec::new();
+    for args in command_args {
+        if let Some(existing) = merged.iter_mut().find(|existing| {
+            existing.executable == args.executable
+                && existing.launch == args.launch
+                && existing.browser_type == args.browser_type
+                && existing.profile.profile_path == args.profile.profile_path
+                && existing.container == args.container
+        }) {
+            existing.uris.extend(args.uris);
+        } else {
+            merged.push(args);
+        }
+    }
+    merged
+}
+
+/// Makes the launched program outlive plinks
+pub fn detach(command: &mut Command) -> &mut Command {
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x210);
+    #[cfg(target_os = "linux")]
+    command.process_group(0);
+    command
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct AppCache {
     pub cfg_version: u8,
     pub installations: Vec<Installation>,
 }
 
-impl Default for AppCache {
-    fn default() -> Self { Self { cfg_version: 0, installations: Vec::new() } }
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AppConfig {
+    pub cfg_version: u8,
+    /// Tried in order before opening the dialog, the first matching rule is used
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Programs opening non-browser schemes, tried before the rules
+    #[serde(default)]
+    pub handlers: Vec<SchemeHandler>,
+    #[serde(default)]
+    pub tracking: TrackingConfig,
+    #[serde(default)]
+    pub unwrap: UnwrapConfig,
+    /// Applied to the URI after unwrapping it, before routing
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+    #[serde(default)]
+    pub homograph: HomographConfig,
+    #[serde(default)]
+    pub policy: SchemePolicy,
+    #[serde(default)]
+    pub script: ScriptConfig,
+    #[serde(default)]
+    pub dialog: DialogConfig,
+}
+
+impl AppConfig {
+    /// Makes the relative domain list paths of the rules and the script path relative to the
+    /// config directory, instead of the working directory
+    pub fn resolve_paths(&mut self, config_dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = config_dir.join(&*path);
+            }
+        };
+        for rule in &mut self.rules {
+            if let Some(domain_list) = &mut rule.matcher.domain_list {
+                resolve(domain_list);
+            }
+        }
+        if let Some(script) = &mut self.script.path {
+            resolve(script);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_by_target, AppConfig, CommandArguments};
+    use crate::profile_loader::{Browsers, LaunchStyle, Profile};
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+
+    fn firefox_args(uri: &str, profile: &str, container: Option<&str>) -> CommandArguments {
+        CommandArguments {
+            uris: vec![uri.to_string()],
+            browser_type: Browsers::Firefox,
+            executable: PathBuf::from("/bin/firefox"),
+            launch: LaunchStyle::Executable,
+            profile: toml::from_str::<Profile>(&format!(
+                "name = \"{profile}\"\nprofile_path = \"/p/{profile}\""
+            ))
+            .unwrap(),
+            container: container.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_config_dir() {
+        let mut config: AppConfig = toml::from_str(
+            r#"
+cfg_version = 0
+
+[[rules]]
+domain_list = "lists/work.txt"
+installation = "Firefox"
+profile = "/p"
+
+[[rules]]
+domain_list = "/etc/plinks/other.txt"
+installation = "Firefox"
+profile = "/p"
+
+[script]
+path = "route.rhai"
+"#,
+        )
+        .unwrap();
+        config.resolve_paths(Path::new("/home/a/.config/plinks"));
+        assert_eq!(
+            config.rules[0].matcher.domain_list.as_deref(),
+            Some(Path::new("/home/a/.config/plinks/lists/work.txt"))
+        );
+        assert_eq!(
+            config.rules[1].matcher.domain_list.as_deref(),
+            Some(Path::new("/etc/plinks/other.txt"))
+        );
+        assert_eq!(
+            config.script.path.as_deref(),
+            Some(Path::new("/home/a/.config/plinks/route.rhai"))
+        );
+    }
+
+    #[test]
+    fn uris_for_the_same_target_are_merged() {
+        let flatpak = CommandArguments {
+            launch: LaunchStyle::Flatpak {
+                app_id: "org.mozilla.firefox".to_string(),
+            },
+            ..firefox_args("f", "work", None)
+        };
+        let merged = merge_by_target(vec![
+            firefox_args("a", "work", None),
+            firefox_args("b", "home", None),
+            firefox_args("c", "work", Some("Bank")),
+            firefox_args("d", "work", None),
+            flatpak,
+            firefox_args("e", "work", Some("Bank")),
+        ]);
+        let uris: Vec<&[String]> = merged.iter().map(|args| args.uris.as_slice()).collect();
+        assert_eq!(
+            uris,
+            vec![
+                &["a".to_string(), "d".to_string()][..],
+                &["b".to_string()],
+                &["c".to_string(), "e".to_string()],
+                &["f".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_browsers_get_every_uri_in_place_of_the_placeholder() {
+        let browser_type: Browsers = toml::from_str(
+            r#"
+[Custom]
+exe = "browser"
+name = "Browser"
+hard_profiles = []
+
+[Custom.args]
+uri_index = 2
+profile_index = 1
+args = ["--profile", "{profile}", "{uri}", "--new-window"]
+"#,
+        )
+        .unwrap();
+        let command = CommandArguments {
+            uris: vec!["https://a.test/".to_string(), "https://b.test/".to_string()],
+            browser_type,
+            ..firefox_args("", "work", None)
+        }
+        .create_command();
+        assert_eq!(command.get_program(), OsStr::new("/bin/firefox"));
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec![
+                OsStr::new("--profile"),
+                OsStr::new("/p/work"),
+                OsStr::new("https://a.test/"),
+                OsStr::new("https://b.test/"),
+                OsStr::new("--new-window"),
+            ]
+        );
+    }
 }
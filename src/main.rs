@@ -1,22 +1,92 @@
 #![feature(explicit_tail_calls)]
 #![expect(incomplete_features)]
+// `become` is unused until it stops breaking inquire, see `ui::console::open_dialog`
+#![allow(unused_features)]
 
-use crate::app::AppCache;
-use crate::profile_loader::{Browsers, Installation};
-use clap::Parser;
-use log::{debug, error, info, trace, warn};
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use crate::app::{AppCache, AppConfig};
+use crate::profile_loader::{Installation, Profile, ProfileMetadata, ProfileOrder};
+use crate::router::import::ImportFormat;
+use crate::router::network::SystemProbe;
+use crate::router::opener::Opener;
+use crate::router::RouteContext;
+use chrono::NaiveDateTime;
+use clap::{Parser, Subcommand};
+use log::{error, info};
 use panic::setup_panic;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 mod app;
 pub mod profile_loader;
+pub mod router;
 pub mod ui;
+pub mod uri;
+
+/// Exit code when at least one URI was refused by the scheme policy
+const EXIT_REFUSED: i32 = 3;
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
-    uri: Option<String>,
+    /// URIs opened at once, unless routed elsewhere by the rules they share a dialog
+    uris: Vec<String>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Manage the routing rules in the config
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommands,
+    },
+    /// Route a URI like opening it directly would
+    Route {
+        uri: String,
+        /// Only explain how the URI would be routed, without opening anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the explanation as JSON
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+        /// Pretend the URI was opened from the executable at this path
+        #[arg(long)]
+        opener: Option<PathBuf>,
+        /// Pretend the URI was opened at this local time, e.g. `2025-03-03T09:30:00`
+        #[arg(long)]
+        at: Option<NaiveDateTime>,
+        /// Read the network state below this directory instead of `/`, e.g. from a copy of
+        /// `sys/class/net`, `proc/net/route` and `etc/resolv.conf`
+        #[arg(long)]
+        network_root: Option<PathBuf>,
+    },
+    /// List the cached installations with their profiles, the default profile first
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        sort: ProfileOrder,
+        /// Show the size of every profile, which reads all of their files
+        #[arg(long)]
+        size: bool,
+    },
+    /// Convert the rules of Finicky, or a JSON rule list, into routing rules
+    ImportRules {
+        /// The format of the file, only Finicky configs and plinks' own JSON rule lists are
+        /// supported, not the rules of Choosy or Browserosaurus
+        #[arg(long, value_enum)]
+        from: ImportFormat,
+        file: PathBuf,
+        /// Add the converted rules to the end of the config, instead of printing them
+        #[arg(long)]
+        append: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RulesCommands {
+    /// Report shadowed, duplicate and invalid rules, exiting with 1 if any are found
+    Check,
 }
 
 fn main() {
@@ -34,46 +104,305 @@ fn main() {
     };
 
     let cli = Args::parse();
+    // Read before anything else, while the parent process is most likely still alive
+    let context = RouteContext::from_environment();
 
-    if let Some(uri) = cli.uri {
-        if let Err(err) = std::fs::File::create(
-            std::env::current_exe()
-                .unwrap() // TODO Error handling
-                .parent()
-                .unwrap() // current_exe should never be root, unwrap is fine
-                .join("config\\last_url.txt"),
-        )
-        .and_then(|mut f| f.write(uri.as_bytes()))
-        {
-            error!("Failed to write last url with error: {}", err);
-        }
-        let mut cache: AppCache = load_cache();
-        #[allow(clippy::zombie_processes)] // Ideally we are detaching the new process
-        ui::open_dialog(uri, &mut cache.installations)
-            .unwrap() // TODO Error handling
-            .spawn()
-            .expect("panic message"); // TODO Error handling
-        save_cache(&cache);
+    if let Some(command) = cli.command {
+        run_command(command, context);
+    } else if !cli.uris.is_empty() {
+        open_uris(
+            cli.uris.iter().map(|arg| uri_from_arg(arg)).collect(),
+            &context,
+        );
     } else {
         // let mut cache: AppCache = load_cache();
         let cache = AppCache {
             cfg_version: 0,
-            installations: vec![
-                Installation::from_installation_path(
-                    PathBuf::from(r"C:\Program Files\Mozilla Firefox\").as_ref(),
-                )
-                .unwrap(),
-                Installation::from_installation_path(
-                    PathBuf::from(r"C:\Portables\LibreWolfPortable\").as_ref(),
-                )
-                .unwrap(),
-            ],
+            installations: profile_loader::default_installation_paths()
+                .iter()
+                .filter_map(|path| Installation::from_installation_path(path).ok())
+                .chain(profile_loader::find_sandboxed_installations())
+                .collect(),
         };
         save_cache(&cache);
         // ui::open_config(&cache.installations);
     }
 }
 
+fn print_installation(installation: &Installation, sort: ProfileOrder, with_size: bool) {
+    println!("{}", installation.get_name());
+    println!("  Executable: {}", installation.exe_path.display());
+    if let Some(general) = installation.profiles_ini_general() {
+        println!(
+            "  profiles.ini: version {}, {}",
+            general.version.as_deref().unwrap_or("unknown"),
+            if general.start_with_last_profile {
+                "starts with the default profile"
+            } else {
+                "asks for a profile on start"
+            }
+        );
+    }
+    let mut profiles: Vec<(&Profile, ProfileMetadata)> = installation
+        .profiles
+        .iter()
+        .map(|p| (p, p.metadata(with_size)))
+        .collect();
+    sort.sort(&mut profiles);
+    profiles.sort_by_key(|(p, _)| installation.default_profile.as_ref() != Some(&p.profile_path));
+    for (profile, metadata) in profiles {
+        let is_default = installation.default_profile.as_ref() == Some(&profile.profile_path);
+        println!(
+            "  {}{}: {}",
+            profile,
+            if is_default { " (default)" } else { "" },
+            profile.profile_path.display()
+        );
+        if metadata != ProfileMetadata::default() {
+            println!("    {metadata}");
+        }
+        let containers = profile.containers();
+        if !containers.is_empty() {
+            println!(
+                "    Containers: {}",
+                containers
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+}
+
+/// File associations hand over plain paths, which are turned into `file://` URIs here
+fn uri_from_arg(arg: &str) -> String {
+    match std::env::current_dir() {
+        Ok(working_dir) => uri::file::path_to_uri(arg, &working_dir),
+        Err(err) => {
+            error!("Failed to get the working directory with error: {}", err);
+            arg.to_string()
+        }
+    }
+}
+
+fn open_uris(uris: Vec<String>, context: &RouteContext) {
+    if let Err(err) = std::fs::File::create(
+        std::env::current_exe()
+            .unwrap() // TODO Error handling
+            .parent()
+            .unwrap() // current_exe should never be root, unwrap is fine
+            .join("config")
+            .join("last_url.txt"),
+    )
+    .and_then(|mut f| f.write(uris.join("\n").as_bytes()))
+    {
+        error!("Failed to write last url with error: {}", err);
+    }
+    let config: AppConfig = load_routing_config();
+    let mut cache: AppCache = load_cache();
+    let mut command_args = Vec::new();
+    let mut unrouted = Vec::new();
+    let mut warnings = Vec::new();
+    let mut refused = false;
+    for uri in uris {
+        let plan = router::explain::plan(
+            &uri,
+            &config,
+            &cache.installations,
+            context,
+            ui::confirm_uri,
+        );
+        let explanation = plan.explanation;
+        if let Some(refusal) = &explanation.refusal {
+            eprintln!("Refused to open {}: it {refusal}", explanation.routed_uri);
+            refused = true;
+            continue;
+        }
+        if let Some(handler_index) = explanation.handler_index {
+            #[allow(clippy::zombie_processes)] // Ideally we are detaching the new process
+            config.handlers[handler_index]
+                .create_command(&explanation.routed_uri)
+                .spawn()
+                .expect("panic message"); // TODO Error handling
+            continue;
+        }
+        match plan.command_args {
+            Some(args) => command_args.push(args),
+            None => {
+                warnings.extend(explanation.homograph_warnings);
+                unrouted.push(explanation.routed_uri);
+            }
+        }
+    }
+    if !unrouted.is_empty() {
+        let unrouted_count = unrouted.len();
+        // Cancelling only skips the URIs of the dialog, routed ones are opened regardless
+        if let Some(mut dialog_args) =
+            ui::open_dialog(unrouted, warnings, &mut cache.installations, &config.dialog)
+        {
+            config.tracking.apply(&mut dialog_args, None);
+            command_args.push(dialog_args);
+        } else {
+            info!("Dialog cancelled, not opening {} URIs", unrouted_count);
+        }
+    }
+    for args in app::merge_by_target(command_args) {
+        #[allow(clippy::zombie_processes)] // Ideally we are detaching the new process
+        args.create_command().spawn().expect("panic message"); // TODO Error handling
+    }
+    save_cache(&cache);
+    if refused {
+        std::process::exit(EXIT_REFUSED);
+    }
+}
+
+fn run_command(command: Commands, mut context: RouteContext) {
+    match command {
+        Commands::Rules {
+            command: RulesCommands::Check,
+        } => {
+            let config: AppConfig = load_routing_config();
+            let cache: AppCache = load_cache();
+            let reports = router::check::check_rules(&config.rules, &cache.installations);
+            for report in &reports {
+                println!("{report}");
+            }
+            if !reports.is_empty() {
+                std::process::exit(1);
+            }
+            println!("All {} rules are fine", config.rules.len());
+        }
+        Commands::Route {
+            uri,
+            dry_run,
+            json,
+            opener,
+            at,
+            network_root,
+        } => {
+            if let Some(opener) = opener {
+                context.opener = Some(Opener::from_path(opener));
+            }
+            if let Some(at) = at {
+                context.now = at;
+            }
+            if let Some(network_root) = network_root {
+                context.network = Rc::new(SystemProbe::with_root(network_root));
+            }
+            let uri = uri_from_arg(&uri);
+            if !dry_run {
+                open_uris(vec![uri], &context);
+                return;
+            }
+            let config: AppConfig = load_routing_config();
+            let cache: AppCache = load_cache();
+            let explanation =
+                router::explain::explain(&uri, &config, &cache.installations, &context);
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&explanation).unwrap() // Only plain data
+                );
+            } else {
+                print!("{explanation}");
+            }
+            if explanation.refusal.is_some() {
+                std::process::exit(EXIT_REFUSED);
+            }
+        }
+        Commands::List { sort, size } => {
+            let cache: AppCache = load_cache();
+            for installation in &cache.installations {
+                print_installation(installation, sort, size || sort == ProfileOrder::Size);
+            }
+        }
+        Commands::ImportRules { from, file, append } => {
+            let source = std::fs::read_to_string(&file).unwrap(); // TODO Error handling
+            let cache: AppCache = load_cache();
+            let report = match router::import::import_rules(from, &source, &cache.installations) {
+                Ok(report) => report,
+                Err(err) => {
+                    eprintln!("Failed to import {}: {}", file.display(), err);
+                    std::process::exit(1);
+                }
+            };
+            for unmapped in &report.unmapped {
+                eprintln!("Not imported: {unmapped}");
+            }
+            if append {
+                let mut config: AppConfig = load_config();
+                config.rules.extend(report.rules);
+                save_config(&config);
+            } else {
+                #[derive(serde::Serialize)]
+                struct Imported {
+                    rules: Vec<router::Rule>,
+                }
+                let imported = Imported {
+                    rules: report.rules,
+                };
+                print!("{}", toml::to_string(&imported).unwrap()); // Only plain data
+            }
+        }
+    }
+}
+
+/// The config with its relative paths resolved, which is only to be routed with, not saved
+fn load_routing_config() -> AppConfig {
+    let mut config = load_config();
+    config.resolve_paths(&config_dir());
+    config
+}
+#[cfg(feature = "portable")]
+fn config_dir() -> PathBuf {
+    std::env::current_exe()
+        .unwrap() // TODO Error handling
+        .parent()
+        .unwrap() // current_exe should never be root, unwrap is fine
+        .join("config")
+}
+#[cfg(not(feature = "portable"))]
+fn config_dir() -> PathBuf {
+    confy::get_configuration_file_path("plinks", "config")
+        .unwrap() // TODO Error handling
+        .parent()
+        .unwrap() // The config file is always in a directory
+        .to_path_buf()
+}
+#[cfg(feature = "portable")]
+fn load_config() -> AppConfig {
+    confy::load_path(
+        std::env::current_exe()
+            .unwrap() // TODO Error handling
+            .parent()
+            .unwrap() // current_exe should never be root, unwrap is fine
+            .join("config")
+            .join("config.toml"),
+    )
+    .unwrap() // TODO Error handling
+}
+#[cfg(not(feature = "portable"))]
+fn load_config() -> AppConfig {
+    confy::load("plinks", "config").unwrap() // TODO Error handling
+}
+#[cfg(feature = "portable")]
+fn save_config(config: &AppConfig) {
+    confy::store_path(
+        std::env::current_exe()
+            .unwrap() // TODO Error handling
+            .parent()
+            .unwrap() // current_exe should never be root, unwrap is fine
+            .join("config")
+            .join("config.toml"),
+        config,
+    )
+    .unwrap(); // TODO Error handling
+}
+#[cfg(not(feature = "portable"))]
+fn save_config(config: &AppConfig) {
+    confy::store("plinks", "config", config).unwrap(); // TODO Error handling
+}
 #[cfg(feature = "portable")]
 fn load_cache() -> AppCache {
     confy::load_path(
@@ -81,7 +410,8 @@ fn load_cache() -> AppCache {
             .unwrap() // TODO Error handling
             .parent()
             .unwrap() // current_exe should never be root, unwrap is fine
-            .join("config\\cache.toml"),
+            .join("config")
+            .join("cache.toml"),
     )
     .unwrap() // TODO Error handling
 }
@@ -96,7 +426,8 @@ fn save_cache(cache: &AppCache) {
             .unwrap() // TODO Error handling
             .parent()
             .unwrap() // current_exe should never be root, unwrap is fine
-            .join("config\\cache.toml"),
+            .join("config")
+            .join("cache.toml"),
         cache,
     )
     .unwrap(); // TODO Error handling
@@ -1,13 +1,16 @@
+use chrono::{DateTime, Local};
 use directories::BaseDirs;
 use ini_roundtrip as ini;
-use log::{debug, error, info, trace, warn};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+mod city_hash;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct ArgConstructor {
     uri_index: usize,
@@ -19,17 +22,18 @@ impl ArgConstructor {
         &'_ self,
         command: &'a mut Command,
         profile_path: &'_ Path,
-        uri: &'_ str,
+        uris: &'_ [String],
     ) -> &'a mut Command {
         let mut args: Vec<&OsStr> = self.args.iter().map(OsStr::new).collect();
-        args[self.uri_index] = OsStr::new(uri);
         args[self.profile_index] = profile_path.as_os_str();
+        // Every URI takes the place of the one argument
+        args.splice(self.uri_index..=self.uri_index, uris.iter().map(OsStr::new));
         command.args(args)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct CustomBrowser {
+pub struct CustomBrowser {
     exe: String,
     name: String,
     args: ArgConstructor,
@@ -47,6 +51,11 @@ pub enum Browsers {
     FirefoxPortable,
     Librewolf,
     LibrewolfPortable,
+    Chrome,
+    Chromium,
+    Brave,
+    Vivaldi,
+    Edge,
     Custom(CustomBrowser),
 }
 impl Browsers {
@@ -79,87 +88,193 @@ impl Browsers {
             if entry.file_name() == "LibreWolf-Portable.exe" {
                 return Ok(Some(Browsers::LibrewolfPortable));
             }
+            #[cfg(target_os = "windows")]
+            if entry.file_name() == "chrome.exe" {
+                // Chromium builds are named like Chrome, but not installed to a `Google` directory
+                return Ok(Some(
+                    if installation_path
+                        .components()
+                        .any(|component| component.as_os_str() == "Chromium")
+                    {
+                        Browsers::Chromium
+                    } else {
+                        Browsers::Chrome
+                    },
+                ));
+            }
+            #[cfg(target_os = "windows")]
+            if entry.file_name() == "brave.exe" {
+                return Ok(Some(Browsers::Brave));
+            }
+            #[cfg(target_os = "windows")]
+            if entry.file_name() == "vivaldi.exe" {
+                return Ok(Some(Browsers::Vivaldi));
+            }
+            #[cfg(target_os = "windows")]
+            if entry.file_name() == "msedge.exe" {
+                return Ok(Some(Browsers::Edge));
+            }
+            #[cfg(target_os = "linux")]
+            if entry.file_name() == "firefox" && !entry.file_type()?.is_dir() {
+                return Ok(Some(Self::differentiate_firefox_release(installation_path)));
+            }
+            #[cfg(target_os = "linux")]
+            if entry.file_name() == "librewolf" && !entry.file_type()?.is_dir() {
+                return Ok(Some(Browsers::Librewolf));
+            }
+            #[cfg(target_os = "linux")]
+            if !entry.file_type()?.is_dir() {
+                match entry.file_name().to_str() {
+                    Some("chrome") => return Ok(Some(Browsers::Chrome)),
+                    // Debian and Ubuntu name the binary after their package
+                    Some("chromium" | "chromium-browser") => return Ok(Some(Browsers::Chromium)),
+                    Some("brave") => return Ok(Some(Browsers::Brave)),
+                    Some("vivaldi") => return Ok(Some(Browsers::Vivaldi)),
+                    Some("msedge") => return Ok(Some(Browsers::Edge)),
+                    _ => {}
+                }
+            }
         }
         Ok(None)
     }
     #[must_use]
-    pub fn find_profiles(&self, installation_path: &Path) -> Vec<Profile> {
+    pub fn find_profiles(&self, installation_path: &Path, launch: &LaunchStyle) -> Vec<Profile> {
         let mut profiles: Vec<Profile> = Vec::new();
         match self {
             Browsers::Firefox
             | Browsers::FirefoxNightly
             | Browsers::FirefoxDeveloper
-            | Browsers::FirefoxBeta =>
-            {
-                #[cfg(target_os = "windows")]
-                if let Some(base_dirs) = BaseDirs::new() {
-                    if let Ok(mut profile_ini) = parse_profiles_ini(
-                        &base_dirs
-                            .config_dir()
-                            .join("Mozilla\\Firefox\\profiles.ini"),
-                    ) {
-                        profiles.append(&mut profile_ini);
-                    }
-                }
+            | Browsers::FirefoxBeta => {
+                profiles.extend(profiles_from_ini(firefox_profiles_ini(launch)));
             }
             Browsers::FirefoxPortable => {
-                #[cfg(target_os = "windows")]
-                if installation_path.join("Data\\profile").exists() {
+                let portable_profile = installation_path.join("Data").join("profile");
+                if portable_profile.exists() {
                     profiles.push(Profile {
                         name: "FirefoxPortable".parse().unwrap(),
-                        profile_path: installation_path.join("Data\\profile"),
+                        profile_path: portable_profile,
+                        is_default: false,
+                        kind: None,
                     });
                 }
-                #[cfg(target_os = "windows")]
-                if let Some(base_dirs) = BaseDirs::new() {
-                    if let Ok(mut profile_ini) = parse_profiles_ini(
-                        &base_dirs
-                            .config_dir()
-                            .join("Mozilla\\Firefox\\profiles.ini"),
-                    ) {
-                        profiles.append(&mut profile_ini);
-                    }
-                }
+                profiles.extend(profiles_from_ini(firefox_profiles_ini(launch)));
             }
-            Browsers::Librewolf =>
-            {
-                #[cfg(target_os = "windows")]
-                if let Some(base_dirs) = BaseDirs::new() {
-                    if let Ok(mut profile_ini) =
-                        parse_profiles_ini(&base_dirs.config_dir().join("librewolf\\profiles.ini"))
-                    {
-                        profiles.append(&mut profile_ini);
-                    }
-                }
+            Browsers::Librewolf => {
+                profiles.extend(profiles_from_ini(librewolf_profiles_ini(launch)));
             }
             Browsers::LibrewolfPortable => {
-                #[cfg(target_os = "windows")]
-                if installation_path.join("Profiles\\Default").exists() {
+                let portable_profile = installation_path.join("Profiles").join("Default");
+                if portable_profile.exists() {
                     profiles.push(Profile {
                         name: "LibrewolfPortable".parse().unwrap(),
-                        profile_path: installation_path.join("Profiles\\Default"),
+                        profile_path: portable_profile,
+                        is_default: false,
+                        kind: None,
                     });
                 }
-                #[cfg(target_os = "windows")]
-                if let Some(base_dirs) = BaseDirs::new() {
-                    if let Ok(mut profile_ini) =
-                        parse_profiles_ini(&base_dirs.config_dir().join("librewolf\\profiles.ini"))
-                    {
-                        profiles.append(&mut profile_ini);
+                profiles.extend(profiles_from_ini(librewolf_profiles_ini(launch)));
+            }
+            Browsers::Chrome
+            | Browsers::Chromium
+            | Browsers::Brave
+            | Browsers::Vivaldi
+            | Browsers::Edge => {
+                if let Some(user_data_dir) = self.chromium_user_data_dir(launch) {
+                    if let Ok(mut local_state) = parse_local_state(&user_data_dir) {
+                        profiles.append(&mut local_state);
                     }
                 }
             }
             Browsers::Custom(custom) => {
-                if let Some(profile_ini_path) = &custom.profile_ini {
-                    if let Ok(mut profile_ini) = parse_profiles_ini(profile_ini_path) {
-                        profiles.append(&mut profile_ini);
-                    }
-                }
-                profiles.append(&mut custom.hard_profiles.clone())
+                profiles.extend(profiles_from_ini(custom.profile_ini.clone()));
+                profiles.extend(custom.hard_profiles.iter().cloned());
             }
         }
         profiles
     }
+    /// The `profiles.ini` listing the profiles, for browsers using one
+    #[must_use]
+    pub fn profiles_ini(&self, launch: &LaunchStyle) -> Option<PathBuf> {
+        match self {
+            Browsers::Firefox
+            | Browsers::FirefoxNightly
+            | Browsers::FirefoxDeveloper
+            | Browsers::FirefoxBeta
+            | Browsers::FirefoxPortable => firefox_profiles_ini(launch),
+            Browsers::Librewolf | Browsers::LibrewolfPortable => librewolf_profiles_ini(launch),
+            Browsers::Chrome
+            | Browsers::Chromium
+            | Browsers::Brave
+            | Browsers::Vivaldi
+            | Browsers::Edge => None,
+            Browsers::Custom(custom) => custom.profile_ini.clone(),
+        }
+    }
+    /// The path of the profile the installation opens when started without one
+    #[must_use]
+    pub fn find_default_profile(
+        &self,
+        installation_path: &Path,
+        launch: &LaunchStyle,
+    ) -> Option<PathBuf> {
+        match self {
+            Browsers::Firefox
+            | Browsers::FirefoxNightly
+            | Browsers::FirefoxDeveloper
+            | Browsers::FirefoxBeta => install_default_profile(
+                &firefox_profiles_ini(launch)?,
+                &launch.install_dir(installation_path, self.get_exe_name()),
+            ),
+            Browsers::FirefoxPortable => {
+                let portable_profile = installation_path.join("Data").join("profile");
+                if portable_profile.exists() {
+                    return Some(portable_profile);
+                }
+                install_default_profile(
+                    &firefox_profiles_ini(launch)?,
+                    &launch.install_dir(installation_path, self.get_exe_name()),
+                )
+            }
+            Browsers::Librewolf => install_default_profile(
+                &librewolf_profiles_ini(launch)?,
+                &launch.install_dir(installation_path, self.get_exe_name()),
+            ),
+            Browsers::LibrewolfPortable => {
+                let portable_profile = installation_path.join("Profiles").join("Default");
+                if portable_profile.exists() {
+                    return Some(portable_profile);
+                }
+                install_default_profile(
+                    &librewolf_profiles_ini(launch)?,
+                    &launch.install_dir(installation_path, self.get_exe_name()),
+                )
+            }
+            Browsers::Chrome
+            | Browsers::Chromium
+            | Browsers::Brave
+            | Browsers::Vivaldi
+            | Browsers::Edge => {
+                let user_data_dir = self.chromium_user_data_dir(launch)?;
+                // Chromium browsers reopen the profile used last
+                let last_used = std::fs::read_to_string(user_data_dir.join("Local State"))
+                    .ok()
+                    .and_then(|local_state| {
+                        serde_json::from_str::<serde_json::Value>(&local_state).ok()
+                    })
+                    .and_then(|local_state| {
+                        local_state
+                            .pointer("/profile/last_used")
+                            .and_then(serde_json::Value::as_str)
+                            .map(str::to_string)
+                    });
+                Some(user_data_dir.join(last_used.as_deref().unwrap_or("Default")))
+            }
+            Browsers::Custom(custom) => install_default_profile(
+                custom.profile_ini.as_ref()?,
+                &launch.install_dir(installation_path, self.get_exe_name()),
+            ),
+        }
+    }
     #[must_use]
     pub fn get_name(&self) -> &str {
         match self {
@@ -170,6 +285,11 @@ impl Browsers {
             Browsers::FirefoxPortable => "Firefox Portable",
             Browsers::Librewolf => "Librewolf",
             Browsers::LibrewolfPortable => "Librewolf Portable",
+            Browsers::Chrome => "Google Chrome",
+            Browsers::Chromium => "Chromium",
+            Browsers::Brave => "Brave",
+            Browsers::Vivaldi => "Vivaldi",
+            Browsers::Edge => "Microsoft Edge",
             Browsers::Custom(custom) => &custom.name,
         }
     }
@@ -184,9 +304,43 @@ impl Browsers {
             Browsers::FirefoxPortable => "FirefoxPortable.exe",
             Browsers::Librewolf => "librewolf.exe",
             Browsers::LibrewolfPortable => "LibrewolfPortable.exe",
+            Browsers::Chrome | Browsers::Chromium => "chrome.exe",
+            Browsers::Brave => "brave.exe",
+            Browsers::Vivaldi => "vivaldi.exe",
+            Browsers::Edge => "msedge.exe",
+            Browsers::Custom(custom) => &custom.exe,
+        }
+        // The portable versions only exist for Windows
+        #[cfg(not(target_os = "windows"))]
+        match self {
+            Browsers::Firefox
+            | Browsers::FirefoxNightly
+            | Browsers::FirefoxDeveloper
+            | Browsers::FirefoxBeta
+            | Browsers::FirefoxPortable => "firefox",
+            Browsers::Librewolf | Browsers::LibrewolfPortable => "librewolf",
+            Browsers::Chrome => "chrome",
+            Browsers::Chromium => "chromium",
+            Browsers::Brave => "brave",
+            Browsers::Vivaldi => "vivaldi",
+            Browsers::Edge => "msedge",
             Browsers::Custom(custom) => &custom.exe,
         }
     }
+    /// The executable in the installation directory, `get_exe_name` unless only a differently
+    /// named one detected by `detect_type` exists
+    #[must_use]
+    pub fn get_exe_path(&self, installation_path: &Path) -> PathBuf {
+        let exe_path = installation_path.join(self.get_exe_name());
+        #[cfg(target_os = "linux")]
+        if *self == Browsers::Chromium && !exe_path.exists() {
+            let chromium_browser = installation_path.join("chromium-browser");
+            if chromium_browser.exists() {
+                return chromium_browser;
+            }
+        }
+        exe_path
+    }
     #[must_use]
     pub fn get_icon(&self, installation_path: &Path) -> Option<PathBuf> {
         match self {
@@ -195,7 +349,18 @@ impl Browsers {
             | Browsers::FirefoxDeveloper
             | Browsers::FirefoxBeta
             | Browsers::Librewolf => {
-                let ico = installation_path.join(r"browser\VisualElements\VisualElements_150.png");
+                #[cfg(target_os = "windows")]
+                let ico = installation_path
+                    .join("browser")
+                    .join("VisualElements")
+                    .join("VisualElements_150.png");
+                #[cfg(not(target_os = "windows"))]
+                let ico = installation_path
+                    .join("browser")
+                    .join("chrome")
+                    .join("icons")
+                    .join("default")
+                    .join("default128.png");
                 if ico.exists() {
                     Some(ico)
                 } else {
@@ -203,10 +368,23 @@ impl Browsers {
                 }
             }
             Browsers::LibrewolfPortable | Browsers::FirefoxPortable => {
-                let ico = installation_path.join(
-                    r"LibreWolf\browser\VisualElements\VisualElements_150
-                .png",
-                );
+                let ico = installation_path
+                    .join("LibreWolf")
+                    .join("browser")
+                    .join("VisualElements")
+                    .join("VisualElements_150.png");
+                if ico.exists() {
+                    Some(ico)
+                } else {
+                    None
+                }
+            }
+            Browsers::Chrome
+            | Browsers::Chromium
+            | Browsers::Brave
+            | Browsers::Vivaldi
+            | Browsers::Edge => {
+                let ico = installation_path.join("product_logo_128.png");
                 if ico.exists() {
                     Some(ico)
                 } else {
@@ -216,24 +394,39 @@ impl Browsers {
             Browsers::Custom(custom) => custom.ico_path.clone(),
         }
     }
-    pub fn add_args_to_command<'a>(
-        &self,
-        c: &'a mut Command,
-        profile_path: &Path,
-        uri: &str,
-    ) -> &'a mut Command {
-        match self {
-            Browsers::Firefox
-            | Browsers::FirefoxNightly
-            | Browsers::FirefoxBeta
-            | Browsers::FirefoxDeveloper
-            | Browsers::FirefoxPortable
-            | Browsers::Librewolf
-            | Browsers::LibrewolfPortable => {
-                c.arg("--profile").arg(profile_path).arg("-url").arg(uri)
-            }
-            Browsers::Custom(custom) => custom.args.construct_args(c, profile_path, uri),
+    /// The directory holding the `Local State` and the profile directories of a Chromium browser
+    ///
+    /// `None` for other browsers.
+    #[must_use]
+    pub fn chromium_user_data_dir(&self, launch: &LaunchStyle) -> Option<PathBuf> {
+        #[cfg(target_os = "windows")]
+        if launch == &LaunchStyle::Executable {
+            let components: &[&str] = match self {
+                Browsers::Chrome => &["Google", "Chrome", "User Data"],
+                Browsers::Chromium => &["Chromium", "User Data"],
+                Browsers::Brave => &["BraveSoftware", "Brave-Browser", "User Data"],
+                Browsers::Vivaldi => &["Vivaldi", "User Data"],
+                Browsers::Edge => &["Microsoft", "Edge", "User Data"],
+                _ => return None,
+            };
+            return Some(components.iter().fold(
+                BaseDirs::new()?.data_local_dir().to_path_buf(),
+                |dir, component| dir.join(component),
+            ));
         }
+        let components: &[&str] = match self {
+            Browsers::Chrome => &["google-chrome"],
+            Browsers::Chromium => &["chromium"],
+            Browsers::Brave => &["BraveSoftware", "Brave-Browser"],
+            Browsers::Vivaldi => &["vivaldi"],
+            Browsers::Edge => &["microsoft-edge"],
+            _ => return None,
+        };
+        Some(
+            components
+                .iter()
+                .fold(launch.config_dir()?, |dir, component| dir.join(component)),
+        )
     }
     #[cfg(target_os = "windows")]
     fn differentiate_firefox_release(installation_path: &Path) -> Browsers {
@@ -254,16 +447,352 @@ impl Browsers {
             panic!("Wtf. Why did powershell give non-utf8.") // TODO handle more gracefully
         }
     }
+    /// Reads the update channel the installation was built for from its default prefs
+    #[cfg(target_os = "linux")]
+    fn differentiate_firefox_release(installation_path: &Path) -> Browsers {
+        let channel_prefs = installation_path
+            .join("defaults")
+            .join("pref")
+            .join("channel-prefs.js");
+        let Ok(channel_prefs) = std::fs::read_to_string(&channel_prefs)
+            .inspect_err(|err| debug!("Failed to read {:?}: {}", channel_prefs, err))
+        else {
+            return Browsers::Firefox;
+        };
+        if channel_prefs.contains(r#""nightly""#) {
+            Browsers::FirefoxNightly
+        } else if channel_prefs.contains(r#""beta""#) {
+            Browsers::FirefoxBeta
+        } else if channel_prefs.contains(r#""aurora""#) {
+            Browsers::FirefoxDeveloper
+        } else {
+            Browsers::Firefox
+        }
+    }
+}
+
+/// The profiles of the `profiles.ini`, if there is one and it can be read
+fn profiles_from_ini(profiles_ini: Option<PathBuf>) -> Vec<Profile> {
+    profiles_ini
+        .and_then(|profiles_ini| parse_profiles_ini(&profiles_ini).ok())
+        .map(|profiles_ini| profiles_ini.profiles)
+        .unwrap_or_default()
+}
+
+/// The name Firefox gives the install in the directory, the `CityHash64` of its UTF-16 path
+///
+/// Formatted like Firefox does with `PRIX64`, without leading zeros.
+fn install_hash(install_dir: &Path) -> String {
+    let utf16: Vec<u8> = install_dir
+        .to_string_lossy()
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    format!("{:X}", city_hash::city_hash_64(&utf16))
+}
+
+/// The default profile of the install from the `[Install<hash>]` sections of the `profiles.ini`,
+/// or else the `installs.ini` next to it
+fn install_default_profile(profiles_ini: &Path, install_dir: &Path) -> Option<PathBuf> {
+    let mut defaults = parse_install_defaults(profiles_ini, "Install").unwrap_or_default();
+    if let Ok(installs_ini) =
+        parse_install_defaults(&profiles_ini.with_file_name("installs.ini"), "")
+    {
+        for (hash, default) in installs_ini {
+            defaults.entry(hash).or_insert(default);
+        }
+    }
+    let default = defaults.remove(&install_hash(install_dir));
+    let Some(default) = default else {
+        debug!(
+            "No default profile for the install at {} in {}",
+            install_dir.display(),
+            profiles_ini.display()
+        );
+        return None;
+    };
+    // Relative to the `profiles.ini`, like the `Path` of relative profiles
+    if Path::new(&default).is_absolute() {
+        Some(PathBuf::from(default))
+    } else {
+        Some(profiles_ini.parent()?.join(default))
+    }
+}
+
+/// The `Default` of every section named after an install hash, keyed by the uppercase hash
+///
+/// # Errors
+///
+/// If the file can't be read
+fn parse_install_defaults(
+    ini_path: &Path,
+    section_prefix: &str,
+) -> Result<HashMap<String, String>, std::io::Error> {
+    let mut defaults = HashMap::new();
+    let mut hash: Option<String> = None;
+    for item in ini::Parser::new(&std::fs::read_to_string(ini_path)?) {
+        match item {
+            ini::Item::Section { name, .. } => {
+                hash = name
+                    .strip_prefix(section_prefix)
+                    .filter(|hash| !hash.is_empty())
+                    .map(str::to_uppercase);
+            }
+            ini::Item::SectionEnd => hash = None,
+            ini::Item::Property {
+                key: "Default",
+                val: Some(default),
+                ..
+            } => {
+                if let Some(hash) = &hash {
+                    defaults.insert(hash.clone(), default.to_string());
+                }
+            }
+            _ => continue,
+        }
+    }
+    Ok(defaults)
+}
+
+/// The `profiles.ini` Firefox and its Nightly, Beta and Developer editions share
+fn firefox_profiles_ini(launch: &LaunchStyle) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    if launch == &LaunchStyle::Executable {
+        return Some(
+            BaseDirs::new()?
+                .config_dir()
+                .join("Mozilla")
+                .join("Firefox")
+                .join("profiles.ini"),
+        );
+    }
+    Some(
+        launch
+            .home_dir()?
+            .join(".mozilla")
+            .join("firefox")
+            .join("profiles.ini"),
+    )
+}
+
+fn librewolf_profiles_ini(launch: &LaunchStyle) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    if launch == &LaunchStyle::Executable {
+        return Some(
+            BaseDirs::new()?
+                .config_dir()
+                .join("librewolf")
+                .join("profiles.ini"),
+        );
+    }
+    Some(launch.home_dir()?.join(".librewolf").join("profiles.ini"))
+}
+
+/// The directories browsers are installed to by default, which might not exist
+#[must_use]
+pub fn default_installation_paths() -> Vec<PathBuf> {
+    #[cfg(target_os = "windows")]
+    return [
+        r"C:\Program Files\Mozilla Firefox",
+        r"C:\Program Files\LibreWolf",
+        r"C:\Program Files\Google\Chrome\Application",
+        r"C:\Program Files\BraveSoftware\Brave-Browser\Application",
+        r"C:\Program Files (x86)\Microsoft\Edge\Application",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    #[cfg(not(target_os = "windows"))]
+    return [
+        "/usr/lib/firefox",
+        "/usr/lib64/firefox",
+        "/usr/lib/firefox-esr",
+        "/usr/lib/firefox-developer-edition",
+        "/usr/lib/firefox-nightly",
+        "/opt/firefox",
+        "/usr/lib/librewolf",
+        "/usr/share/librewolf",
+        "/opt/librewolf",
+        "/opt/google/chrome",
+        "/usr/lib/chromium",
+        "/usr/lib/chromium-browser",
+        "/opt/brave.com/brave",
+        "/opt/vivaldi",
+        "/opt/microsoft/msedge",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+}
+
+/// How an installation is started
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum LaunchStyle {
+    /// Running the `exe_path` of the installation, or one of its symlinks
+    #[default]
+    Executable,
+    /// `flatpak run <app_id>`, with the profiles in `~/.var/app/<app_id>`
+    Flatpak { app_id: String },
+    /// `snap run <name>`, with the profiles in `~/snap/<name>/common`
+    Snap { name: String },
+}
+
+impl LaunchStyle {
+    /// Starts building the command, `executable` is only run for `Executable`
+    #[must_use]
+    pub fn command(&self, executable: &Path) -> Command {
+        match self {
+            LaunchStyle::Executable => Command::new(executable),
+            LaunchStyle::Flatpak { app_id } => {
+                let mut command = Command::new("flatpak");
+                command.arg("run").arg(app_id);
+                command
+            }
+            LaunchStyle::Snap { name } => {
+                let mut command = Command::new("snap");
+                command.arg("run").arg(name);
+                command
+            }
+        }
+    }
+    /// The directory the browser keeps dotfiles like `.mozilla` in
+    #[must_use]
+    pub fn home_dir(&self) -> Option<PathBuf> {
+        let home_dir = BaseDirs::new()?.home_dir().to_path_buf();
+        match self {
+            LaunchStyle::Executable => Some(home_dir),
+            LaunchStyle::Flatpak { app_id } => Some(home_dir.join(".var").join("app").join(app_id)),
+            LaunchStyle::Snap { name } => Some(home_dir.join("snap").join(name).join("common")),
+        }
+    }
+    /// The directory the browser sees as `XDG_CONFIG_HOME`
+    #[must_use]
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        match self {
+            LaunchStyle::Executable => Some(BaseDirs::new()?.config_dir().to_path_buf()),
+            LaunchStyle::Flatpak { .. } => Some(self.home_dir()?.join("config")),
+            LaunchStyle::Snap { .. } => self.home_dir(),
+        }
+    }
+    /// The directory the browser is installed to, as the browser itself sees it
+    ///
+    /// Gecko browsers name their installs after it. Flatpaks are installed to `/app/lib/<exe>`,
+    /// Snaps run from their current revision.
+    #[must_use]
+    pub fn install_dir(&self, installation_path: &Path, exe_name: &str) -> PathBuf {
+        let install_dir = match self {
+            LaunchStyle::Executable => installation_path.to_path_buf(),
+            LaunchStyle::Flatpak { .. } => return Path::new("/app/lib").join(exe_name),
+            LaunchStyle::Snap { name } => Path::new("/snap")
+                .join(name)
+                .join("current")
+                .join("usr")
+                .join("lib")
+                .join(exe_name),
+        };
+        // Firefox resolves symlinks, on Windows that would add a `\\?\` prefix though
+        #[cfg(not(target_os = "windows"))]
+        return install_dir.canonicalize().unwrap_or(install_dir);
+        #[cfg(target_os = "windows")]
+        install_dir
+    }
+    /// Translates a path to where the sandboxed browser sees it
+    ///
+    /// Flatpaks see their `~/.var/app/<app_id>` as the home directory, Snaps can access their
+    /// directories by the real path.
+    #[must_use]
+    pub fn sandboxed_path(&self, path: &Path) -> PathBuf {
+        if let LaunchStyle::Flatpak { .. } = self {
+            if let (Some(sandbox_home), Some(base_dirs)) = (self.home_dir(), BaseDirs::new()) {
+                if let Ok(relative) = path.strip_prefix(sandbox_home) {
+                    return base_dirs.home_dir().join(relative);
+                }
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+/// Flatpak app ids of the supported browsers
+const FLATPAKS: &[(&str, Browsers)] = &[
+    ("org.mozilla.firefox", Browsers::Firefox),
+    ("io.gitlab.librewolf-community", Browsers::Librewolf),
+    ("com.google.Chrome", Browsers::Chrome),
+    ("org.chromium.Chromium", Browsers::Chromium),
+    ("com.brave.Browser", Browsers::Brave),
+    ("com.vivaldi.Vivaldi", Browsers::Vivaldi),
+    ("com.microsoft.Edge", Browsers::Edge),
+];
+
+/// Snap names of the supported browsers
+const SNAPS: &[(&str, Browsers)] = &[
+    ("firefox", Browsers::Firefox),
+    ("chromium", Browsers::Chromium),
+    ("brave", Browsers::Brave),
+];
+
+/// Looks for browsers installed as system-wide or per-user Flatpaks and as Snaps
+#[must_use]
+pub fn find_sandboxed_installations() -> Vec<Installation> {
+    let mut installations = Vec::new();
+    let mut flatpak_dirs = vec![PathBuf::from("/var/lib/flatpak")];
+    if let Some(base_dirs) = BaseDirs::new() {
+        flatpak_dirs.push(base_dirs.data_dir().join("flatpak"));
+    }
+    for (app_id, browser_type) in FLATPAKS {
+        let Some(flatpak_dir) = flatpak_dirs
+            .iter()
+            .find(|dir| dir.join("app").join(app_id).exists())
+        else {
+            continue;
+        };
+        let launch = LaunchStyle::Flatpak {
+            app_id: (*app_id).to_string(),
+        };
+        let exe_path = flatpak_dir.join("exports").join("bin").join(app_id);
+        match Installation::from_sandbox(browser_type.clone(), launch, exe_path) {
+            Ok(installation) => installations.push(installation),
+            Err(err) => info!("Skipping the Flatpak {}: {}", app_id, err),
+        }
+    }
+    for (name, browser_type) in SNAPS {
+        if !Path::new("/snap").join(name).exists() {
+            continue;
+        }
+        let launch = LaunchStyle::Snap {
+            name: (*name).to_string(),
+        };
+        let exe_path = Path::new("/snap").join("bin").join(name);
+        match Installation::from_sandbox(browser_type.clone(), launch, exe_path) {
+            Ok(installation) => installations.push(installation),
+            Err(err) => info!("Skipping the Snap {}: {}", name, err),
+        }
+    }
+    installations
+}
+
+/// The profile marked with `Default=1`, for installs without a default of their own
+fn legacy_default_profile(profiles: &[Profile]) -> Option<PathBuf> {
+    profiles
+        .iter()
+        .find(|p| p.is_default)
+        .map(|p| p.profile_path.clone())
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Installation {
     pub name: Option<String>,
     pub browser_type: Browsers,
+    /// For sandboxed installations only the launcher of the sandbox, which isn't run
     pub exe_path: PathBuf,
+    #[serde(default)]
+    pub launch: LaunchStyle,
     pub symlinks: Vec<PathBuf>,
     pub preferred: HashMap<PathBuf, PathBuf>,
     pub profiles: Vec<Profile>,
+    /// The `profile_path` of the profile the browser opens when started without one
+    #[serde(default)]
+    pub default_profile: Option<PathBuf>,
     pub last_used: Option<Profile>,
 }
 
@@ -282,24 +811,65 @@ impl Installation {
         let Some(type_) = Browsers::detect_type(installation_path)? else {
             return Err(std::io::Error::other("Unknown browser type"));
         };
-        let profiles = type_.find_profiles(installation_path);
+        let profiles = type_.find_profiles(installation_path, &LaunchStyle::Executable);
         if profiles.is_empty() {
             return Err(std::io::Error::other("No profiles found"));
         }
+        let default_profile = type_
+            .find_default_profile(installation_path, &LaunchStyle::Executable)
+            .filter(|default| profiles.iter().any(|p| &p.profile_path == default))
+            .or_else(|| legacy_default_profile(&profiles));
         Ok(Installation {
             name: None,
             browser_type: type_.clone(),
-            exe_path: installation_path.join(type_.get_exe_name()),
+            exe_path: type_.get_exe_path(installation_path),
+            launch: LaunchStyle::Executable,
             symlinks: Vec::new(),
             preferred: HashMap::default(),
             profiles,
+            default_profile,
+            last_used: None,
+        })
+    }
+    /// Create an Installation started through a sandbox, named after the browser and sandbox
+    ///
+    /// # Errors
+    ///
+    /// Returns a custom `io::Error` with `ErrorKind::Other` if no profiles could be found
+    pub fn from_sandbox(
+        browser_type: Browsers,
+        launch: LaunchStyle,
+        exe_path: PathBuf,
+    ) -> Result<Self, std::io::Error> {
+        let profiles = browser_type.find_profiles(&exe_path, &launch);
+        if profiles.is_empty() {
+            return Err(std::io::Error::other("No profiles found"));
+        }
+        let default_profile = browser_type
+            .find_default_profile(&exe_path, &launch)
+            .filter(|default| profiles.iter().any(|p| &p.profile_path == default))
+            .or_else(|| legacy_default_profile(&profiles));
+        let sandbox = match launch {
+            LaunchStyle::Executable => "Executable",
+            LaunchStyle::Flatpak { .. } => "Flatpak",
+            LaunchStyle::Snap { .. } => "Snap",
+        };
+        Ok(Installation {
+            name: Some(format!("{} ({})", browser_type.get_name(), sandbox)),
+            browser_type,
+            exe_path,
+            launch,
+            symlinks: Vec::new(),
+            preferred: HashMap::default(),
+            profiles,
+            default_profile,
             last_used: None,
         })
     }
     #[inline]
     #[must_use]
     pub fn get_icon(&self) -> Option<PathBuf> {
-        self.browser_type.get_icon(&self.exe_path)
+        self.browser_type.get_icon(self.exe_path.parent()?)
     }
     #[inline]
     #[must_use]
@@ -321,6 +891,27 @@ impl Installation {
         }
         Ok(false)
     }
+    /// The `[General]` settings of the installation's `profiles.ini`, if it has one
+    #[must_use]
+    pub fn profiles_ini_general(&self) -> Option<ProfilesIniGeneral> {
+        let profiles_ini = self.browser_type.profiles_ini(&self.launch)?;
+        parse_profiles_ini(&profiles_ini)
+            .inspect_err(|err| debug!("Failed to read {:?}: {}", profiles_ini, err))
+            .ok()
+            .map(|profiles_ini| profiles_ini.general)
+    }
+    /// Looks up the profile by its `profile_path`, or the default profile for `default`
+    #[must_use]
+    pub fn find_profile(&self, profile_path: &Path) -> Option<&Profile> {
+        let profile_path = if profile_path == Path::new("default") {
+            self.default_profile.as_deref()?
+        } else {
+            profile_path
+        };
+        self.profiles
+            .iter()
+            .find(|p| p.profile_path == profile_path)
+    }
     pub fn remove_symlink(&mut self, installation_path: &Path) -> bool {
         if let Some(index) = self.symlinks.iter().position(|p| p == installation_path) {
             self.symlinks.remove(index);
@@ -341,9 +932,101 @@ impl Display for Installation {
 pub struct Profile {
     name: String,
     pub profile_path: PathBuf,
+    /// Marked with `Default=1` in the `profiles.ini`, which installs without a default of their
+    /// own open
+    #[serde(default)]
+    pub is_default: bool,
+    /// How to hand the profile to the browser, the browser type's usual way when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ProfileKind>,
+}
+
+/// How a profile is handed to the browser
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ProfileKind {
+    /// `--profile <profile_path>`, for Gecko profiles of any directory
+    GeckoPath,
+    /// `-P <name>`, for Gecko profiles listed in the browser's own `profiles.ini`
+    ///
+    /// Only set by hand, as it opens the first profile of the name when several share it.
+    GeckoNamed,
+    /// `--profile-directory=<dir>`, the directory inside the user data directory
+    ChromiumDirectory,
+    /// The `profile_path` put in place of the profile argument of the custom browser
+    Custom,
+}
+
+impl ProfileKind {
+    /// The kind profiles of the browser type are handed over as unless set otherwise
+    #[must_use]
+    pub fn default_for(browser_type: &Browsers) -> ProfileKind {
+        match browser_type {
+            Browsers::Firefox
+            | Browsers::FirefoxNightly
+            | Browsers::FirefoxBeta
+            | Browsers::FirefoxDeveloper
+            | Browsers::FirefoxPortable
+            | Browsers::Librewolf
+            | Browsers::LibrewolfPortable => ProfileKind::GeckoPath,
+            Browsers::Chrome
+            | Browsers::Chromium
+            | Browsers::Brave
+            | Browsers::Vivaldi
+            | Browsers::Edge => ProfileKind::ChromiumDirectory,
+            Browsers::Custom(_) => ProfileKind::Custom,
+        }
+    }
 }
 
 impl Profile {
+    /// The kind the profile is handed over as in the browser type
+    #[must_use]
+    pub fn kind(&self, browser_type: &Browsers) -> ProfileKind {
+        self.kind
+            .clone()
+            .unwrap_or_else(|| ProfileKind::default_for(browser_type))
+    }
+    /// Adds the arguments opening the URIs in the profile
+    pub fn add_args_to_command<'a>(
+        &self,
+        c: &'a mut Command,
+        browser_type: &Browsers,
+        launch: &LaunchStyle,
+        uris: &[String],
+    ) -> &'a mut Command {
+        let profile_path = launch.sandboxed_path(&self.profile_path);
+        let kind = self.kind(browser_type);
+        match kind {
+            ProfileKind::GeckoPath | ProfileKind::GeckoNamed => {
+                if kind == ProfileKind::GeckoNamed {
+                    c.arg("-P").arg(&self.name);
+                } else {
+                    c.arg("--profile").arg(profile_path);
+                }
+                for uri in uris {
+                    c.arg("-url").arg(uri);
+                }
+                c
+            }
+            ProfileKind::ChromiumDirectory => {
+                let mut profile_directory = OsString::from("--profile-directory=");
+                profile_directory.push(profile_path.file_name().unwrap_or_default());
+                c.arg(profile_directory).args(uris)
+            }
+            ProfileKind::Custom => {
+                if let Browsers::Custom(custom) = browser_type {
+                    custom.args.construct_args(c, &profile_path, uris)
+                } else {
+                    warn!(
+                        "Profile {} is custom, but {} isn't a custom browser",
+                        self.name,
+                        browser_type.get_name()
+                    );
+                    c.args(uris)
+                }
+            }
+        }
+    }
     #[must_use]
     pub fn is_open(&self) -> bool {
         let lockfile = self.profile_path.join("parent.lock");
@@ -355,6 +1038,133 @@ impl Profile {
         // Presume other possible errors would cause the exists to fail first
         std::fs::File::open(lockfile).is_err()
     }
+    /// The containers of the profile's `containers.json`, empty for browsers without containers
+    #[must_use]
+    pub fn containers(&self) -> Vec<Container> {
+        let containers_json = self.profile_path.join("containers.json");
+        if !containers_json.exists() {
+            return Vec::new();
+        }
+        parse_containers_json(&containers_json)
+            .inspect_err(|err| warn!("Failed to read {:?}: {}", containers_json, err))
+            .unwrap_or_default()
+    }
+    /// Reads the metadata from the profile directory, summing up its size only if `with_size`
+    #[must_use]
+    pub fn metadata(&self, with_size: bool) -> ProfileMetadata {
+        let created = std::fs::read_to_string(self.profile_path.join("times.json"))
+            .ok()
+            .and_then(|times| serde_json::from_str::<serde_json::Value>(&times).ok())
+            .and_then(|times| times.get("created").and_then(serde_json::Value::as_i64))
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|created| created.with_timezone(&Local));
+        // Written by Gecko and Chromium browsers respectively while they are used
+        let last_used = [
+            "sessionstore.jsonlz4",
+            "sessionstore-backups/recovery.jsonlz4",
+            "prefs.js",
+            "Preferences",
+            "Sessions",
+        ]
+        .iter()
+        .filter_map(|file| std::fs::metadata(self.profile_path.join(file)).ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+        .map(DateTime::<Local>::from);
+        ProfileMetadata {
+            created,
+            last_used,
+            size: (with_size && self.profile_path.is_dir()).then(|| dir_size(&self.profile_path)),
+        }
+    }
+}
+
+/// The total size of the files below the directory, without following symlinks
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map_or(0, |m| m.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// What is known about a profile besides its name, read when shown instead of being cached
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfileMetadata {
+    /// From the `times.json` of Gecko profiles
+    pub created: Option<DateTime<Local>>,
+    /// When the session or preferences were written last
+    pub last_used: Option<DateTime<Local>>,
+    /// In bytes, only if requested since it takes a walk over the whole profile
+    pub size: Option<u64>,
+}
+
+impl Display for ProfileMetadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(last_used) = self.last_used {
+            parts.push(format!("last used {}", last_used.format("%Y-%m-%d %H:%M")));
+        }
+        if let Some(created) = self.created {
+            parts.push(format!("created {}", created.format("%Y-%m-%d")));
+        }
+        match self.size {
+            Some(size) if size < 1024 => parts.push(format!("{size} B")),
+            Some(size) => {
+                #[expect(clippy::cast_precision_loss)] // Only shown with one decimal
+                let mut size = size as f64 / 1024.0;
+                let mut unit = "KiB";
+                for next_unit in ["MiB", "GiB", "TiB"] {
+                    if size < 1024.0 {
+                        break;
+                    }
+                    size /= 1024.0;
+                    unit = next_unit;
+                }
+                parts.push(format!("{size:.1} {unit}"));
+            }
+            None => {}
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// How profiles are ordered when listed, the most recent and largest first
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProfileOrder {
+    /// As found in the `profiles.ini` or `Local State`
+    #[default]
+    Listed,
+    Name,
+    LastUsed,
+    Created,
+    Size,
+}
+
+impl ProfileOrder {
+    /// Sorts profiles together with their metadata, which needs the size for `Size`
+    pub fn sort(self, profiles: &mut [(&Profile, ProfileMetadata)]) {
+        match self {
+            ProfileOrder::Listed => {}
+            ProfileOrder::Name => profiles.sort_by_key(|(profile, _)| profile.name.to_lowercase()),
+            ProfileOrder::LastUsed => {
+                profiles.sort_by_key(|(_, metadata)| std::cmp::Reverse(metadata.last_used));
+            }
+            ProfileOrder::Created => {
+                profiles.sort_by_key(|(_, metadata)| std::cmp::Reverse(metadata.created));
+            }
+            ProfileOrder::Size => {
+                profiles.sort_by_key(|(_, metadata)| std::cmp::Reverse(metadata.size));
+            }
+        }
+    }
 }
 
 impl Display for Profile {
@@ -363,33 +1173,180 @@ impl Display for Profile {
     }
 }
 
+/// A Firefox container a URI can be opened in, instead of the profile's default context
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Container {
+    pub name: String,
+    pub user_context_id: u64,
+}
+
+impl Container {
+    /// The `ext+container:` URI container extensions open the URI in the container for
+    #[must_use]
+    pub fn wrap_uri(name: &str, uri: &str) -> String {
+        let query: String = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("name", name)
+            .append_pair("url", uri)
+            .finish();
+        format!("ext+container:{query}")
+    }
+}
+
+impl Display for Container {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Lists the containers of the `identities` users can open tabs in
+///
+/// # Errors
+///
+/// If the `containers.json` can't be read or isn't valid JSON
+fn parse_containers_json(containers_json: &Path) -> Result<Vec<Container>, std::io::Error> {
+    let containers: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(containers_json)?)?;
+    let Some(identities) = containers
+        .get("identities")
+        .and_then(serde_json::Value::as_array)
+    else {
+        warn!(
+            "Missing `identities` in the `containers.json` at {}",
+            containers_json.display()
+        );
+        return Ok(Vec::new());
+    };
+    Ok(identities
+        .iter()
+        // Private identities are used by Firefox internally
+        .filter(|identity| {
+            identity.get("public").and_then(serde_json::Value::as_bool) == Some(true)
+        })
+        .filter_map(|identity| {
+            let user_context_id = identity
+                .get("userContextId")
+                .and_then(serde_json::Value::as_u64)?;
+            // The built-in containers are only named by their localisation id until renamed
+            let name = match identity.get("name").and_then(serde_json::Value::as_str) {
+                Some(name) => name.to_string(),
+                None => match identity.get("l10nID").and_then(serde_json::Value::as_str)? {
+                    "userContextPersonal.label" => "Personal".to_string(),
+                    "userContextWork.label" => "Work".to_string(),
+                    "userContextBanking.label" => "Banking".to_string(),
+                    "userContextShopping.label" => "Shopping".to_string(),
+                    _ => return None,
+                },
+            };
+            Some(Container {
+                name,
+                user_context_id,
+            })
+        })
+        .collect())
+}
+
+/// Lists the profiles of a Chromium browser from the `profile.info_cache` of its `Local State`
+///
+/// # Errors
+///
+/// If the `Local State` can't be read or isn't valid JSON
+fn parse_local_state(user_data_dir: &Path) -> Result<Vec<Profile>, std::io::Error> {
+    let local_state_path = user_data_dir.join("Local State");
+    let local_state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&local_state_path)?)?;
+    let Some(info_cache) = local_state
+        .pointer("/profile/info_cache")
+        .and_then(serde_json::Value::as_object)
+    else {
+        warn!(
+            "Missing `profile.info_cache` in the `Local State` at {}",
+            local_state_path.display()
+        );
+        return Ok(Vec::new());
+    };
+    Ok(info_cache
+        .iter()
+        .map(|(directory, info)| Profile {
+            // The display name, which the directory name is only a fallback for
+            name: info
+                .get("name")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or(directory)
+                .to_string(),
+            profile_path: user_data_dir.join(directory),
+            is_default: false,
+            kind: None,
+        })
+        .collect())
+}
+
+/// The `[General]` section of a `profiles.ini`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilesIniGeneral {
+    /// Whether the browser opens the default profile without showing the profile manager
+    pub start_with_last_profile: bool,
+    pub version: Option<String>,
+}
+
+impl Default for ProfilesIniGeneral {
+    fn default() -> Self {
+        Self {
+            start_with_last_profile: true,
+            version: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfilesIni {
+    pub general: ProfilesIniGeneral,
+    pub profiles: Vec<Profile>,
+}
+
+/// Reads the profiles and general settings of a `profiles.ini`
+///
+/// # Errors
+///
+/// If the file can't be read
 #[allow(clippy::too_many_lines)]
-fn parse_profiles_ini(profiles_ini: &Path) -> Result<Vec<Profile>, std::io::Error> {
+pub fn parse_profiles_ini(profiles_ini: &Path) -> Result<ProfilesIni, std::io::Error> {
     #[derive(PartialEq)]
     enum ParserState {
         InProfile,
+        InGeneral,
         Other,
     }
-    use ParserState::{InProfile, Other};
+    use ParserState::{InGeneral, InProfile, Other};
 
     let mut profiles: Vec<Profile> = Vec::new();
+    let mut general = ProfilesIniGeneral::default();
 
     let mut parser_state = Other;
     let mut name_buffer = String::new();
     let mut path_buffer = String::new();
     let mut isrelative_buffer: Option<bool> = None;
+    let mut default_buffer = false;
 
     for item in ini::Parser::new(&std::fs::read_to_string(profiles_ini)?) {
         match item {
             ini::Item::Section {
                 name: section_name, ..
             } => {
-                if section_name.starts_with("Profile") {
-                    parser_state = InProfile;
-                }
+                parser_state = if section_name.starts_with("Profile") {
+                    InProfile
+                } else if section_name == "General" {
+                    InGeneral
+                } else {
+                    Other
+                };
             }
             ini::Item::SectionEnd => {
-                if parser_state == InProfile {
+                if std::mem::replace(&mut parser_state, Other) == InProfile {
+                    // Taken before the checks, so a skipped section leaves nothing to the next
+                    let name_buffer = std::mem::take(&mut name_buffer);
+                    let path_buffer = std::mem::take(&mut path_buffer);
+                    let isrelative_buffer = isrelative_buffer.take();
+                    let default_buffer = std::mem::take(&mut default_buffer);
                     if name_buffer.is_empty() {
                         warn!(
                             "Missing `Name` key in a `Profile` section in the `profiles.ini` at {}",
@@ -418,12 +1375,10 @@ fn parse_profiles_ini(profiles_ini: &Path) -> Result<Vec<Profile>, std::io::Erro
                         } else {
                             path_buffer.parse().unwrap() // TODO Proper error handling 4 future me
                         },
+                        is_default: default_buffer,
+                        kind: None,
                     });
-                    name_buffer = String::new();
-                    path_buffer = String::new();
-                    isrelative_buffer = None;
-                    parser_state = Other;
-                };
+                }
             }
             ini::Item::Property {
                 key: "Name",
@@ -492,8 +1447,346 @@ fn parse_profiles_ini(profiles_ini: &Path) -> Result<Vec<Profile>, std::io::Erro
                     );
                 }
             }
+            // `[Install…]` sections have a `Default` too, holding a path
+            ini::Item::Property {
+                key: "Default",
+                val,
+                ..
+            } if parser_state == InProfile => {
+                default_buffer = val.is_some_and(|val| val.trim() == "1");
+            }
+            ini::Item::Property {
+                key: "StartWithLastProfile",
+                val: Some(val),
+                ..
+            } if parser_state == InGeneral => {
+                general.start_with_last_profile = val.trim() == "1";
+            }
+            ini::Item::Property {
+                key: "Version",
+                val: Some(val),
+                ..
+            } if parser_state == InGeneral => {
+                general.version = Some(val.trim().to_string());
+            }
             _ => continue,
         }
     }
-    Ok(profiles)
+    Ok(ProfilesIni { general, profiles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        install_default_profile, install_hash, parse_containers_json, parse_local_state,
+        parse_profiles_ini, Browsers, Container, Profile, ProfileKind, ProfileMetadata,
+        ProfileOrder, ProfilesIniGeneral,
+    };
+    use chrono::{DateTime, Local};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A fresh directory below the temp dir holding the files
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("plinks-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn install_hash_has_no_leading_zeros() {
+        assert_eq!(
+            install_hash(Path::new("/usr/lib/firefox")),
+            "4F96D1932A9F858E"
+        );
+        assert_eq!(install_hash(Path::new("/opt/firefox")), "6AFDA46A1A8AD48");
+    }
+
+    #[test]
+    fn missing_kind_follows_browser_type() {
+        let profile: Profile = toml::from_str(
+            r#"
+name = "Work"
+profile_path = "/p/Profile 1"
+"#,
+        )
+        .unwrap();
+        assert_eq!(profile.kind(&Browsers::Firefox), ProfileKind::GeckoPath);
+        assert_eq!(
+            profile.kind(&Browsers::Chrome),
+            ProfileKind::ChromiumDirectory
+        );
+        let named: Profile = toml::from_str(
+            r#"
+name = "Work"
+profile_path = "/p"
+kind = "GeckoNamed"
+"#,
+        )
+        .unwrap();
+        assert_eq!(named.kind(&Browsers::Firefox), ProfileKind::GeckoNamed);
+    }
+
+    #[test]
+    fn local_state_lists_the_info_cache() {
+        let user_data_dir = fixture(
+            "local-state",
+            &[(
+                "Local State",
+                r#"{"profile": {"last_used": "Profile 1", "info_cache": {
+                    "Default": {"name": "Person 1"},
+                    "Profile 1": {"name": "Work"},
+                    "Profile 2": {}
+                }}}"#,
+            )],
+        );
+        let mut profiles: Vec<(String, PathBuf)> = parse_local_state(&user_data_dir)
+            .unwrap()
+            .into_iter()
+            .map(|profile| (profile.name, profile.profile_path))
+            .collect();
+        profiles.sort();
+        assert_eq!(
+            profiles,
+            vec![
+                ("Person 1".to_string(), user_data_dir.join("Default")),
+                ("Profile 2".to_string(), user_data_dir.join("Profile 2")),
+                ("Work".to_string(), user_data_dir.join("Profile 1")),
+            ]
+        );
+        fs::remove_dir_all(user_data_dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_local_states_are_errors() {
+        let missing = fixture("local-state-missing", &[]);
+        assert!(parse_local_state(&missing).is_err());
+        let invalid = fixture("local-state-invalid", &[("Local State", "{\"profile\": ")]);
+        assert!(parse_local_state(&invalid).is_err());
+        let without_cache = fixture("local-state-empty", &[("Local State", "{}")]);
+        assert_eq!(parse_local_state(&without_cache).unwrap(), Vec::new());
+        for dir in [missing, invalid, without_cache] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn profiles_ini_sections() {
+        let dir = fixture(
+            "profiles-ini",
+            &[
+                (
+                    "profiles.ini",
+                    "[General]\nStartWithLastProfile=0\nVersion=2\n\n\
+                     [Profile0]\nName=Broken\nIsRelative=1\nDefault=1\n\n\
+                     [Profile1]\nName=Work\nIsRelative=1\nPath=abc.work\n\n\
+                     [Profile2]\nName=Home\nIsRelative=0\nPath=/data/home\nDefault=1\n\n\
+                     [Install4F96D1932A9F858E]\nDefault=abc.work\nLocked=1\n",
+                ),
+                ("installs.ini", "[6AFDA46A1A8AD48]\nDefault=/data/home\n"),
+            ],
+        );
+        let profiles_ini = parse_profiles_ini(&dir.join("profiles.ini")).unwrap();
+        assert_eq!(
+            profiles_ini.general,
+            ProfilesIniGeneral {
+                start_with_last_profile: false,
+                version: Some("2".to_string()),
+            }
+        );
+        let profiles: Vec<(&str, &Path, bool)> = profiles_ini
+            .profiles
+            .iter()
+            .map(|profile| {
+                (
+                    profile.name.as_str(),
+                    profile.profile_path.as_path(),
+                    profile.is_default,
+                )
+            })
+            .collect();
+        // The section without a `Path` is skipped without leaving its `Default` to the next
+        assert_eq!(
+            profiles,
+            vec![
+                ("Work", dir.join("abc.work").as_path(), false),
+                ("Home", Path::new("/data/home"), true),
+            ]
+        );
+        assert_eq!(
+            install_default_profile(&dir.join("profiles.ini"), Path::new("/usr/lib/firefox")),
+            Some(dir.join("abc.work"))
+        );
+        assert_eq!(
+            install_default_profile(&dir.join("profiles.ini"), Path::new("/opt/firefox")),
+            Some(PathBuf::from("/data/home"))
+        );
+        assert_eq!(
+            install_default_profile(&dir.join("profiles.ini"), Path::new("/usr/bin")),
+            None
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn public_containers_are_listed() {
+        let dir = fixture(
+            "containers",
+            &[(
+                "containers.json",
+                r#"{"version": 5, "identities": [
+                    {"userContextId": 1, "public": true, "l10nID": "userContextPersonal.label"},
+                    {"userContextId": 2, "public": true, "l10nID": "userContextWork.label",
+                     "name": "Office"},
+                    {"userContextId": 3, "public": true, "l10nID": "userContextUnknown.label"},
+                    {"userContextId": 4, "public": false, "name": "userContextIdInternal.thumbnail"},
+                    {"userContextId": 6, "public": true, "name": "Shop & Pay"}
+                ]}"#,
+            )],
+        );
+        let containers = parse_containers_json(&dir.join("containers.json")).unwrap();
+        assert_eq!(
+            containers,
+            vec![
+                Container {
+                    name: "Personal".to_string(),
+                    user_context_id: 1,
+                },
+                Container {
+                    name: "Office".to_string(),
+                    user_context_id: 2,
+                },
+                Container {
+                    name: "Shop & Pay".to_string(),
+                    user_context_id: 6,
+                },
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wrapped_uris_are_percent_encoded() {
+        assert_eq!(
+            Container::wrap_uri("Work", "https://example.com/"),
+            "ext+container:name=Work&url=https%3A%2F%2Fexample.com%2F"
+        );
+        assert_eq!(
+            Container::wrap_uri("Shop & Pay", "https://example.com/a?b=1&c=d e#f"),
+            "ext+container:name=Shop+%26+Pay\
+             &url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1%26c%3Dd+e%23f"
+        );
+    }
+
+    #[test]
+    fn metadata_from_the_profile_directory() {
+        let dir = fixture(
+            "metadata",
+            &[
+                (
+                    "times.json",
+                    r#"{"created": 1700000000000, "firstUse": null}"#,
+                ),
+                ("prefs.js", "user_pref(\"a\", 1);\n"),
+                ("sessionstore-backups/recovery.jsonlz4", "0123456789"),
+            ],
+        );
+        let used = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_750_000_000);
+        fs::File::options()
+            .write(true)
+            .open(dir.join("prefs.js"))
+            .unwrap()
+            .set_modified(used)
+            .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("sessionstore-backups/recovery.jsonlz4"))
+            .unwrap()
+            .set_modified(used - std::time::Duration::from_secs(60))
+            .unwrap();
+        let profile = Profile {
+            name: "Work".to_string(),
+            profile_path: dir.clone(),
+            is_default: false,
+            kind: None,
+        };
+        let metadata = profile.metadata(true);
+        assert_eq!(
+            metadata,
+            ProfileMetadata {
+                created: DateTime::from_timestamp_millis(1_700_000_000_000)
+                    .map(|created| created.with_timezone(&Local)),
+                last_used: Some(DateTime::<Local>::from(used)),
+                size: Some(44 + 19 + 10),
+            }
+        );
+        assert_eq!(profile.metadata(false).size, None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn profiles_without_metadata_keep_their_order() {
+        let profile = |name: &str| Profile {
+            name: name.to_string(),
+            profile_path: PathBuf::from("/p").join(name),
+            is_default: false,
+            kind: None,
+        };
+        let (b, a, c, d) = (profile("b"), profile("a"), profile("c"), profile("d"));
+        let last_used =
+            DateTime::from_timestamp(1_750_000_000, 0).map(|at| at.with_timezone(&Local));
+        for order in [
+            ProfileOrder::Listed,
+            ProfileOrder::LastUsed,
+            ProfileOrder::Created,
+            ProfileOrder::Size,
+        ] {
+            let mut profiles = vec![
+                (&b, ProfileMetadata::default()),
+                (&a, ProfileMetadata::default()),
+                (
+                    &d,
+                    ProfileMetadata {
+                        last_used,
+                        ..ProfileMetadata::default()
+                    },
+                ),
+                (&c, ProfileMetadata::default()),
+            ];
+            order.sort(&mut profiles);
+            let names: Vec<&str> = profiles
+                .iter()
+                .map(|(profile, _)| profile.name.as_str())
+                .collect();
+            if order == ProfileOrder::LastUsed {
+                assert_eq!(names, vec!["d", "b", "a", "c"]);
+            } else {
+                assert_eq!(names, vec!["b", "a", "d", "c"], "{order:?}");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn chromium_browser_binaries_are_chromium() {
+        let dir = fixture("chromium-browser", &[("chromium-browser", "")]);
+        assert_eq!(
+            Browsers::detect_type(&dir).unwrap(),
+            Some(Browsers::Chromium)
+        );
+        assert_eq!(
+            Browsers::Chromium.get_exe_path(&dir),
+            dir.join("chromium-browser")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        let dir = fixture("chromium", &[("chromium", "")]);
+        assert_eq!(Browsers::Chromium.get_exe_path(&dir), dir.join("chromium"));
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,203 @@
+//! `CityHash64` from CityHash 1.0.3, which Firefox names its installs after
+//!
+//! Later CityHash versions changed the results, so this can't be swapped for a crate of them.
+
+const K0: u64 = 0xc3a5_c85c_97cb_3127;
+const K1: u64 = 0xb492_b66f_be98_f273;
+const K2: u64 = 0x9ae1_6a3b_2f90_404f;
+const K3: u64 = 0xc949_d7c7_509e_6557;
+
+fn fetch64(s: &[u8], index: usize) -> u64 {
+    u64::from_le_bytes(s[index..index + 8].try_into().unwrap())
+}
+
+fn fetch32(s: &[u8], index: usize) -> u64 {
+    u64::from(u32::from_le_bytes(s[index..index + 4].try_into().unwrap()))
+}
+
+fn shift_mix(val: u64) -> u64 {
+    val ^ (val >> 47)
+}
+
+fn hash_len_16(u: u64, v: u64) -> u64 {
+    const MUL: u64 = 0x9ddf_ea08_eb38_2d69;
+    let mut a = (u ^ v).wrapping_mul(MUL);
+    a ^= a >> 47;
+    let mut b = (v ^ a).wrapping_mul(MUL);
+    b ^= b >> 47;
+    b.wrapping_mul(MUL)
+}
+
+#[expect(clippy::cast_possible_truncation)] // Only called for up to 16 bytes
+fn hash_len_0_to_16(s: &[u8]) -> u64 {
+    let len = s.len() as u64;
+    if s.len() > 8 {
+        let a = fetch64(s, 0);
+        let b = fetch64(s, s.len() - 8);
+        return hash_len_16(a, b.wrapping_add(len).rotate_right(len as u32)) ^ b;
+    }
+    if s.len() >= 4 {
+        let a = fetch32(s, 0);
+        return hash_len_16(len + (a << 3), fetch32(s, s.len() - 4));
+    }
+    if let (Some(&a), Some(&c)) = (s.first(), s.last()) {
+        let b = s[s.len() >> 1];
+        let y = u32::from(a) + (u32::from(b) << 8);
+        let z = len as u32 + (u32::from(c) << 2);
+        return shift_mix(u64::from(y).wrapping_mul(K2) ^ u64::from(z).wrapping_mul(K3))
+            .wrapping_mul(K2);
+    }
+    K2
+}
+
+fn hash_len_17_to_32(s: &[u8]) -> u64 {
+    let len = s.len();
+    let a = fetch64(s, 0).wrapping_mul(K1);
+    let b = fetch64(s, 8);
+    let c = fetch64(s, len - 8).wrapping_mul(K2);
+    let d = fetch64(s, len - 16).wrapping_mul(K0);
+    hash_len_16(
+        a.wrapping_sub(b)
+            .rotate_right(43)
+            .wrapping_add(c.rotate_right(30))
+            .wrapping_add(d),
+        a.wrapping_add((b ^ K3).rotate_right(20))
+            .wrapping_sub(c)
+            .wrapping_add(len as u64),
+    )
+}
+
+fn hash_len_33_to_64(s: &[u8]) -> u64 {
+    let len = s.len();
+    let mut z = fetch64(s, 24);
+    let mut a = fetch64(s, 0).wrapping_add(
+        (len as u64)
+            .wrapping_add(fetch64(s, len - 16))
+            .wrapping_mul(K0),
+    );
+    let mut b = a.wrapping_add(z).rotate_right(52);
+    let mut c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(s, 8));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(s, 16));
+    let vf = a.wrapping_add(z);
+    let vs = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+    a = fetch64(s, 16).wrapping_add(fetch64(s, len - 32));
+    z = fetch64(s, len - 8);
+    b = a.wrapping_add(z).rotate_right(52);
+    c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(s, len - 24));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(s, len - 16));
+    let wf = a.wrapping_add(z);
+    let ws = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+    let r = shift_mix(
+        vf.wrapping_add(ws)
+            .wrapping_mul(K2)
+            .wrapping_add(wf.wrapping_add(vs).wrapping_mul(K0)),
+    );
+    shift_mix(r.wrapping_mul(K0).wrapping_add(vs)).wrapping_mul(K2)
+}
+
+fn weak_hash_len_32_with_seeds(s: &[u8], index: usize, mut a: u64, mut b: u64) -> (u64, u64) {
+    let w = fetch64(s, index);
+    let x = fetch64(s, index + 8);
+    let y = fetch64(s, index + 16);
+    let z = fetch64(s, index + 24);
+    a = a.wrapping_add(w);
+    b = b.wrapping_add(a).wrapping_add(z).rotate_right(21);
+    let c = a;
+    a = a.wrapping_add(x).wrapping_add(y);
+    b = b.wrapping_add(a.rotate_right(44));
+    (a.wrapping_add(z), b.wrapping_add(c))
+}
+
+pub fn city_hash_64(s: &[u8]) -> u64 {
+    let len = s.len();
+    if len <= 16 {
+        return hash_len_0_to_16(s);
+    }
+    if len <= 32 {
+        return hash_len_17_to_32(s);
+    }
+    if len <= 64 {
+        return hash_len_33_to_64(s);
+    }
+    let mut x = fetch64(s, len - 40);
+    let mut y = fetch64(s, len - 16).wrapping_add(fetch64(s, len - 56));
+    let mut z = hash_len_16(
+        fetch64(s, len - 48).wrapping_add(len as u64),
+        fetch64(s, len - 24),
+    );
+    let mut v = weak_hash_len_32_with_seeds(s, len - 64, len as u64, z);
+    let mut w = weak_hash_len_32_with_seeds(s, len - 32, y.wrapping_add(K1), x);
+    x = x.wrapping_mul(K1).wrapping_add(fetch64(s, 0));
+    // Every full 64 byte chunk, the rest was already mixed into the seeds
+    for index in (0..(len - 1) & !63).step_by(64) {
+        x = x
+            .wrapping_add(y)
+            .wrapping_add(v.0)
+            .wrapping_add(fetch64(s, index + 8))
+            .rotate_right(37)
+            .wrapping_mul(K1);
+        y = y
+            .wrapping_add(v.1)
+            .wrapping_add(fetch64(s, index + 48))
+            .rotate_right(42)
+            .wrapping_mul(K1);
+        x ^= w.1;
+        y = y.wrapping_add(v.0).wrapping_add(fetch64(s, index + 40));
+        z = z.wrapping_add(w.0).rotate_right(33).wrapping_mul(K1);
+        v = weak_hash_len_32_with_seeds(s, index, v.1.wrapping_mul(K1), x.wrapping_add(w.0));
+        w = weak_hash_len_32_with_seeds(
+            s,
+            index + 32,
+            z.wrapping_add(w.1),
+            y.wrapping_add(fetch64(s, index + 16)),
+        );
+        std::mem::swap(&mut z, &mut x);
+    }
+    hash_len_16(
+        hash_len_16(v.0, w.0)
+            .wrapping_add(shift_mix(y).wrapping_mul(K1))
+            .wrapping_add(z),
+        hash_len_16(v.1, w.1).wrapping_add(x),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::city_hash_64;
+
+    fn hash_utf16(path: &str) -> u64 {
+        let utf16: Vec<u8> = path.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        city_hash_64(&utf16)
+    }
+
+    #[test]
+    fn known_install_hashes() {
+        // Hashes Firefox wrote into `installs.ini` for these install directories
+        assert_eq!(hash_utf16("/usr/lib/firefox"), 0x4F96_D193_2A9F_858E);
+        assert_eq!(
+            hash_utf16(r"C:\Program Files\Mozilla Firefox"),
+            0x3080_46B0_AF4A_39CB
+        );
+    }
+
+    #[test]
+    fn every_length_branch() {
+        assert_eq!(city_hash_64(b""), 0x9ae1_6a3b_2f90_404f);
+        assert_eq!(hash_utf16("a"), 0x8235_F11B_3662_0B8C);
+        assert_eq!(hash_utf16("abcdefghijk"), 0x8BFB_807D_4A62_B58F);
+        assert_eq!(
+            hash_utf16(r"C:\Program Files (x86)\Mozilla Firefox"),
+            0x30D2_5EAE_FAEF_0A51
+        );
+        assert_eq!(
+            hash_utf16(
+                "/opt/some/really/long/directory/path/with/many/parts/firefox-nightly-build"
+            ),
+            0x1B7E_32FE_62F5_BBE9
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::profile_loader::Installation;
+use crate::router::domain_list::DomainList;
+use crate::router::{Matcher, Rule};
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleProblem {
+    /// Every URI the rule matches is already matched by the earlier rule
+    Shadowed {
+        by: usize,
+    },
+    /// The rule has exactly the same matcher and conditions as the earlier rule
+    Duplicate {
+        of: usize,
+    },
+    InvalidRegex(String),
+    UnreadableDomainList(String),
+    UnknownInstallation,
+    UnknownProfile,
+    UnknownContainer,
+}
+
+impl Display for RuleProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleProblem::Shadowed { by } => write!(f, "never matches, shadowed by rule {by}"),
+            RuleProblem::Duplicate { of } => {
+                write!(f, "has the same matcher and conditions as rule {of}")
+            }
+            RuleProblem::InvalidRegex(err) => write!(f, "has an invalid regex: {err}"),
+            RuleProblem::UnreadableDomainList(err) => {
+                write!(f, "has a domain list which can't be read: {err}")
+            }
+            RuleProblem::UnknownInstallation => {
+                write!(f, "targets an installation which does not exist")
+            }
+            RuleProblem::UnknownProfile => {
+                write!(
+                    f,
+                    "targets a profile which does not exist in its installation"
+                )
+            }
+            RuleProblem::UnknownContainer => {
+                write!(f, "targets a container which does not exist in its profile")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleReport {
+    pub rule_index: usize,
+    pub problem: RuleProblem,
+}
+
+impl Display for RuleReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rule {} {}", self.rule_index, self.problem)
+    }
+}
+
+/// Looks for rules which can never fire or point at targets which don't exist
+///
+/// Shadowing is detected conservatively: a rule is only reported if an earlier rule provably
+/// matches everything it does. Regexes are only compared by their source and domain lists by
+/// their path. Earlier rules with a target which can't be found shadow nothing, routing skips
+/// them. Rules with the same matcher and conditions as an earlier one are reported as its
+/// duplicate rather than as shadowed.
+#[must_use]
+pub fn check_rules(rules: &[Rule], installations: &[Installation]) -> Vec<RuleReport> {
+    let mut reports = Vec::new();
+    let target_problems: Vec<Option<RuleProblem>> = rules
+        .iter()
+        .map(|rule| target_problem(rule, installations))
+        .collect();
+    for (rule_index, rule) in rules.iter().enumerate() {
+        let mut earlier_rules = rules[..rule_index].iter().enumerate();
+        if let Some((earlier_index, _)) = earlier_rules.clone().find(|(_, earlier)| {
+            earlier.matcher == rule.matcher && earlier.conditions == rule.conditions
+        }) {
+            reports.push(RuleReport {
+                rule_index,
+                problem: RuleProblem::Duplicate { of: earlier_index },
+            });
+        } else if let Some((earlier_index, _)) = earlier_rules.find(|(earlier_index, earlier)| {
+            target_problems[*earlier_index].is_none()
+                && covers(&earlier.matcher, &rule.matcher)
+                && earlier.conditions.covers(&rule.conditions)
+        }) {
+            reports.push(RuleReport {
+                rule_index,
+                problem: RuleProblem::Shadowed { by: earlier_index },
+            });
+        }
+        if let Some(Err(err)) = rule.matcher.regex.as_deref().map(Regex::new) {
+            reports.push(RuleReport {
+                rule_index,
+                problem: RuleProblem::InvalidRegex(err.to_string()),
+            });
+        }
+        if let Some(Err(err)) = rule.matcher.domain_list.as_deref().map(DomainList::load) {
+            reports.push(RuleReport {
+                rule_index,
+                problem: RuleProblem::UnreadableDomainList(err.to_string()),
+            });
+        }
+        if let Some(problem) = &target_problems[rule_index] {
+            reports.push(RuleReport {
+                rule_index,
+                problem: problem.clone(),
+            });
+        }
+    }
+    reports
+}
+
+/// Why the target of the rule can't be found, if it can't
+fn target_problem(rule: &Rule, installations: &[Installation]) -> Option<RuleProblem> {
+    let Some(installation) = installations
+        .iter()
+        .find(|i| i.get_name() == rule.target.installation)
+    else {
+        return Some(RuleProblem::UnknownInstallation);
+    };
+    let Some(profile) = installation.find_profile(&rule.target.profile) else {
+        return Some(RuleProblem::UnknownProfile);
+    };
+    if rule
+        .target
+        .container
+        .as_ref()
+        .is_some_and(|container| !profile.containers().iter().any(|c| &c.name == container))
+    {
+        return Some(RuleProblem::UnknownContainer);
+    }
+    None
+}
+
+/// Whether `broad` matches at least every URI `narrow` matches
+fn covers(broad: &Matcher, narrow: &Matcher) -> bool {
+    if let Some(scheme) = &broad.scheme {
+        if !narrow.scheme.as_ref().is_some_and(|narrow_scheme| {
+            narrow_scheme
+                .trim_end_matches(':')
+                .eq_ignore_ascii_case(scheme.trim_end_matches(':'))
+        }) {
+            return false;
+        }
+    }
+    if let Some(host) = &broad.host {
+        if !narrow
+            .host
+            .as_ref()
+            .is_some_and(|narrow_host| glob_covers(host, narrow_host))
+        {
+            return false;
+        }
+    }
+    if broad.domain_list.is_some() && broad.domain_list != narrow.domain_list {
+        return false;
+    }
+    if let Some(extension) = &broad.extension {
+        if !narrow.extension.as_ref().is_some_and(|narrow_extension| {
+            narrow_extension
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(extension.trim_start_matches('.'))
+        }) {
+            return false;
+        }
+    }
+    if let Some(path_prefix) = &broad.path_prefix {
+        if !narrow
+            .path_prefix
+            .as_ref()
+            .is_some_and(|narrow_prefix| narrow_prefix.starts_with(path_prefix.as_str()))
+        {
+            return false;
+        }
+    }
+    for (broad_glob, narrow_glob) in [
+        (&broad.opener_name, &narrow.opener_name),
+        (&broad.opener_path, &narrow.opener_path),
+    ] {
+        if let Some(glob) = broad_glob {
+            if !narrow_glob
+                .as_ref()
+                .is_some_and(|narrow_glob| glob_covers(glob, narrow_glob))
+            {
+                return false;
+            }
+        }
+    }
+    if broad.regex.is_some() && broad.regex != narrow.regex {
+        return false;
+    }
+    true
+}
+
+/// Whether the glob `broad` matches at least every text the glob `narrow` matches
+///
+/// The wildcards of `narrow` stand for any character, so a `*` in it is only covered by a `*`
+/// and a `?` by a `?` or `*`. Compared case-insensitively like `glob_match`.
+fn glob_covers(broad: &str, narrow: &str) -> bool {
+    let broad: Vec<char> = broad.to_lowercase().chars().collect();
+    let narrow: Vec<char> = narrow.to_lowercase().chars().collect();
+    // covered[b][n]: whether broad[b..] covers narrow[n..]
+    let mut covered = vec![vec![false; narrow.len() + 1]; broad.len() + 1];
+    covered[broad.len()][narrow.len()] = true;
+    for b in (0..broad.len()).rev() {
+        for n in (0..=narrow.len()).rev() {
+            covered[b][n] = match (broad[b], narrow.get(n)) {
+                // Either matches nothing more, or takes the next part of `narrow` as well
+                ('*', next) => covered[b + 1][n] || (next.is_some() && covered[b][n + 1]),
+                (_, None | Some('*')) => false,
+                ('?', Some(_)) => covered[b + 1][n + 1],
+                (c, Some(next)) => c == *next && covered[b + 1][n + 1],
+            };
+        }
+    }
+    covered[0][0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_rules, glob_covers, RuleProblem};
+    use crate::profile_loader::Installation;
+    use crate::router::Rule;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Fixture {
+        rules: Vec<Rule>,
+        #[serde(default)]
+        installations: Vec<Installation>,
+    }
+
+    fn problems(fixture: &str) -> Vec<(usize, RuleProblem)> {
+        let fixture: Fixture = toml::from_str(fixture).unwrap();
+        check_rules(&fixture.rules, &fixture.installations)
+            .into_iter()
+            .map(|report| (report.rule_index, report.problem))
+            .collect()
+    }
+
+    #[test]
+    fn globs_only_cover_wildcards_with_wildcards() {
+        assert!(glob_covers("*.example.com", "www.example.com"));
+        assert!(glob_covers("*.example.com", "*.www.Example.com"));
+        assert!(glob_covers("*", "a?.com"));
+        assert!(glob_covers("a*.com", "a?.com"));
+        assert!(glob_covers("a?.com", "ab.com"));
+        assert!(!glob_covers("a?.com", "a*.com"));
+        assert!(!glob_covers("ab.com", "a?.com"));
+        assert!(!glob_covers("*.example.com", "*example.com"));
+        assert!(!glob_covers("a?.com", "a.com"));
+    }
+
+    const INSTALLATION: &str = r#"
+[[installations]]
+name = "Firefox"
+browser_type = "Firefox"
+exe_path = "/bin/firefox"
+symlinks = []
+profiles = [{ name = "Work", profile_path = "/p" }]
+
+[installations.preferred]
+"#;
+
+    #[test]
+    fn shadowed_by_resolvable_rules_only() {
+        let rules = r#"
+[[rules]]
+host = "*.com"
+installation = "Missing"
+profile = "/p"
+
+[[rules]]
+host = "a*.com"
+installation = "Firefox"
+profile = "/p"
+
+[[rules]]
+host = "a?.com"
+installation = "Firefox"
+profile = "/p"
+
+[[rules]]
+host = "a?.com"
+installation = "Firefox"
+profile = "/p"
+"#;
+        assert_eq!(
+            problems(&format!("{rules}{INSTALLATION}")),
+            vec![
+                (0, RuleProblem::UnknownInstallation),
+                (2, RuleProblem::Shadowed { by: 1 }),
+                (3, RuleProblem::Duplicate { of: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn narrow_wildcards_are_not_shadowed_by_single_characters() {
+        let rules = r#"
+[[rules]]
+host = "a?.com"
+installation = "Firefox"
+profile = "/p"
+
+[[rules]]
+host = "a*.com"
+installation = "Firefox"
+profile = "/p"
+
+[[rules]]
+host = "a*.com"
+installation = "Firefox"
+profile = "/p"
+"#;
+        assert_eq!(
+            problems(&format!("{rules}{INSTALLATION}")),
+            vec![(2, RuleProblem::Duplicate { of: 1 })]
+        );
+    }
+}
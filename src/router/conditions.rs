@@ -0,0 +1,334 @@
+use crate::router::network::{parse_network, route_contains};
+use crate::router::{glob_match, RouteContext};
+use chrono::{Datelike, NaiveTime, Weekday};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Conditions on the circumstances a URI is opened in, rather than on the URI itself
+///
+/// Like with `Matcher`, every set field has to hold and unset fields always hold.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Conditions {
+    /// e.g. `["Mon", "Tue"]`
+    pub weekdays: Option<Vec<Weekday>>,
+    /// Local time range like `"09:00-17:30"`, ranges like `"22:00-06:00"` span midnight
+    pub time: Option<TimeRange>,
+    /// Glob over the name of a network interface which has to be up, e.g. `"tun*"`
+    pub interface_up: Option<String>,
+    /// A network like `"10.20.0.0/16"` or a single address, which some route has to cover
+    pub route: Option<String>,
+    /// Glob over a DNS search domain which has to be configured, e.g. `"corp.example.com"`
+    pub dns_search_domain: Option<String>,
+    /// `"NAME"` for an environment variable which has to be set and not empty, or
+    /// `"NAME=value"` for one which has to have exactly that value
+    pub env: Option<String>,
+}
+
+impl Conditions {
+    #[must_use]
+    pub fn hold(&self, context: &RouteContext) -> bool {
+        if let Some(weekdays) = &self.weekdays {
+            if !weekdays.contains(&context.now.weekday()) {
+                return false;
+            }
+        }
+        if let Some(time) = &self.time {
+            if !time.contains(context.now.time()) {
+                return false;
+            }
+        }
+        if let Some(interface) = &self.interface_up {
+            let interface = interface.to_lowercase();
+            if !context
+                .network
+                .interfaces_up()
+                .iter()
+                .any(|name| glob_match(&interface, name))
+            {
+                return false;
+            }
+        }
+        if let Some(route) = &self.route {
+            match parse_network(route) {
+                Ok(network) => {
+                    if !context
+                        .network
+                        .routes()
+                        .into_iter()
+                        .any(|route| route_contains(route, network))
+                    {
+                        return false;
+                    }
+                }
+                Err(err) => {
+                    warn!("{}, treating the condition as not holding", err);
+                    return false;
+                }
+            }
+        }
+        if let Some(domain) = &self.dns_search_domain {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            if !context
+                .network
+                .search_domains()
+                .iter()
+                .any(|search_domain| glob_match(&domain, search_domain))
+            {
+                return false;
+            }
+        }
+        if let Some(env) = &self.env {
+            let value = match env.split_once('=') {
+                Some((name, expected)) => {
+                    context.env.get(name).is_some_and(|value| value == expected)
+                }
+                None => context.env.get(env).is_some_and(|value| !value.is_empty()),
+            };
+            if !value {
+                return false;
+            }
+        }
+        true
+    }
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Conditions::default()
+    }
+    /// Whether these conditions hold at least whenever `narrow` holds
+    #[must_use]
+    pub fn covers(&self, narrow: &Conditions) -> bool {
+        if let Some(weekdays) = &self.weekdays {
+            if !narrow
+                .weekdays
+                .as_ref()
+                .is_some_and(|narrow_days| narrow_days.iter().all(|day| weekdays.contains(day)))
+            {
+                return false;
+            }
+        }
+        if self.time.is_some() && self.time != narrow.time {
+            return false;
+        }
+        for (broad, narrow) in [
+            (&self.interface_up, &narrow.interface_up),
+            (&self.route, &narrow.route),
+            (&self.dns_search_domain, &narrow.dns_search_domain),
+            (&self.env, &narrow.env),
+        ] {
+            if broad.is_some() && broad != narrow {
+                return false;
+            }
+        }
+        true
+    }
+    /// Human-readable descriptions of every set condition
+    #[must_use]
+    pub fn describe(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+        if let Some(weekdays) = &self.weekdays {
+            descriptions.push(format!(
+                "on {}",
+                weekdays
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        if let Some(time) = &self.time {
+            descriptions.push(format!(
+                "between {} and {}",
+                time.start.format("%H:%M"),
+                time.end.format("%H:%M")
+            ));
+        }
+        if let Some(interface) = &self.interface_up {
+            descriptions.push(format!("while interface `{interface}` is up"));
+        }
+        if let Some(route) = &self.route {
+            descriptions.push(format!("while a route to `{route}` exists"));
+        }
+        if let Some(domain) = &self.dns_search_domain {
+            descriptions.push(format!("while `{domain}` is a DNS search domain"));
+        }
+        if let Some(env) = &self.env {
+            descriptions.push(match env.split_once('=') {
+                Some((name, value)) => format!("while `{name}` is `{value}`"),
+                None => format!("while `{env}` is set"),
+            });
+        }
+        descriptions
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeRange {
+    pub start: NaiveTime,
+    /// Exclusive
+    pub end: NaiveTime,
+}
+
+impl TimeRange {
+    #[must_use]
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.split_once('-') else {
+            return Err(format!("Time range `{s}` is not of the form `HH:MM-HH:MM`"));
+        };
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|err| format!("Invalid time `{time}` in time range `{s}`: {err}"))
+        };
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl TryFrom<String> for TimeRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TimeRange> for String {
+    fn from(value: TimeRange) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conditions, TimeRange};
+    use crate::router::network::NetworkProbe;
+    use crate::router::RouteContext;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+    use std::rc::Rc;
+
+    #[derive(Debug)]
+    struct FakeProbe;
+
+    impl NetworkProbe for FakeProbe {
+        fn interfaces_up(&self) -> Vec<String> {
+            vec!["eth0".to_string(), "tun0".to_string()]
+        }
+        fn routes(&self) -> Vec<(IpAddr, u8)> {
+            vec![("10.20.0.0".parse().unwrap(), 16)]
+        }
+        fn search_domains(&self) -> Vec<String> {
+            vec!["corp.example.com".to_string()]
+        }
+    }
+
+    /// A context at the given time, 2025-03-03 being a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> RouteContext {
+        RouteContext {
+            opener: None,
+            now: NaiveDate::from_ymd_opt(2025, 3, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+            network: Rc::new(FakeProbe),
+            env: HashMap::from([("PROFILE".to_string(), "work".to_string())]),
+        }
+    }
+
+    #[test]
+    fn schedule_against_the_injected_clock() {
+        let work_hours = Conditions {
+            weekdays: Some(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ]),
+            time: Some("09:00-17:30".parse().unwrap()),
+            ..Conditions::default()
+        };
+        assert!(work_hours.hold(&at(3, 9, 0)));
+        assert!(work_hours.hold(&at(7, 17, 29)));
+        assert!(!work_hours.hold(&at(3, 17, 30)));
+        assert!(!work_hours.hold(&at(3, 8, 59)));
+        // Saturday
+        assert!(!work_hours.hold(&at(8, 12, 0)));
+        assert!(Conditions::default().hold(&at(8, 12, 0)));
+    }
+
+    #[test]
+    fn time_ranges() {
+        let night: TimeRange = "22:00-06:00".parse().unwrap();
+        let time = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+        assert!(night.contains(time(23, 0)));
+        assert!(night.contains(time(5, 59)));
+        assert!(!night.contains(time(6, 0)));
+        assert!(!night.contains(time(12, 0)));
+        assert_eq!(night.to_string(), "22:00-06:00");
+        assert!("9-17".parse::<TimeRange>().is_err());
+        assert!("09:00".parse::<TimeRange>().is_err());
+    }
+
+    #[test]
+    fn network_conditions() {
+        let context = at(3, 12, 0);
+        let holds = |conditions: Conditions| conditions.hold(&context);
+        assert!(holds(Conditions {
+            interface_up: Some("TUN*".to_string()),
+            route: Some("10.20.30.0/24".to_string()),
+            dns_search_domain: Some("*.example.com".to_string()),
+            env: Some("PROFILE=work".to_string()),
+            ..Conditions::default()
+        }));
+        assert!(!holds(Conditions {
+            interface_up: Some("wg*".to_string()),
+            ..Conditions::default()
+        }));
+        assert!(!holds(Conditions {
+            route: Some("10.0.0.0/8".to_string()),
+            ..Conditions::default()
+        }));
+        assert!(!holds(Conditions {
+            route: Some("invalid".to_string()),
+            ..Conditions::default()
+        }));
+        assert!(!holds(Conditions {
+            env: Some("PROFILE=personal".to_string()),
+            ..Conditions::default()
+        }));
+        assert!(holds(Conditions {
+            env: Some("PROFILE".to_string()),
+            ..Conditions::default()
+        }));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::router::glob_match;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// One label of a domain in the suffix tree, children are keyed by the next label to the left
+#[derive(Debug, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    /// The domain ending at this node is listed itself
+    exact: bool,
+    /// Every subdomain of the domain ending at this node is listed
+    subdomains: bool,
+}
+
+/// Domains loaded from a list file, matched by walking their labels from the TLD on
+///
+/// Every non-empty line holds one entry, everything after a `#` is a comment. Internationalised
+/// entries may be written in Unicode or Punycode, hosts are compared in Punycode:
+/// - `example.com` matches exactly that host
+/// - `*.example.com` matches every subdomain of it, but not `example.com` itself
+/// - `.example.com` matches `example.com` and every subdomain of it
+/// - Any other glob, like `example.*`, is checked one after the other and should be rare
+#[derive(Debug, Default)]
+pub struct DomainList {
+    root: Node,
+    globs: Vec<String>,
+}
+
+impl DomainList {
+    #[must_use]
+    pub fn parse(source: &str) -> Self {
+        let mut list = Self::default();
+        for line in source.lines() {
+            let entry = line
+                .split_once('#')
+                .map_or(line, |(entry, _)| entry)
+                .trim()
+                .trim_end_matches('.')
+                .split('.')
+                .map(label_to_ascii)
+                .collect::<Vec<_>>()
+                .join(".");
+            if entry.is_empty() {
+                continue;
+            }
+            let (domain, exact, subdomains) = if let Some(domain) = entry.strip_prefix("*.") {
+                (domain, false, true)
+            } else if let Some(domain) = entry.strip_prefix('.') {
+                (domain, true, true)
+            } else {
+                (entry.as_str(), true, false)
+            };
+            if domain.contains(['*', '?']) {
+                list.globs.push(entry.clone());
+                continue;
+            }
+            let node = domain.rsplit('.').fold(&mut list.root, |node, label| {
+                node.children.entry(label.to_string()).or_default()
+            });
+            node.exact |= exact;
+            node.subdomains |= subdomains;
+        }
+        list
+    }
+
+    /// # Errors
+    ///
+    /// Returns the error of reading the file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let list = Self::parse(&std::fs::read_to_string(path)?);
+        info!("Loaded domain list {:?}", path);
+        Ok(list)
+    }
+
+    /// Whether the host, compared case-insensitively, is listed
+    #[must_use]
+    pub fn contains(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let mut node = &self.root;
+        let mut labels = host.rsplit('.').peekable();
+        while let Some(label) = labels.next() {
+            let Some(child) = node.children.get(label) else {
+                break;
+            };
+            node = child;
+            if labels.peek().is_none() {
+                if node.exact {
+                    return true;
+                }
+            } else if node.subdomains {
+                return true;
+            }
+        }
+        self.globs.iter().any(|glob| glob_match(glob, &host))
+    }
+}
+
+/// The label in lowercase Punycode, labels which can't be converted are only lowercased
+fn label_to_ascii(label: &str) -> String {
+    if label.is_ascii() {
+        return label.to_lowercase();
+    }
+    idna::domain_to_ascii(label).unwrap_or_else(|_| label.to_lowercase())
+}
+
+/// Lists stay loaded for the lifetime of the process, so every URI only reads them once
+static LOADED: Mutex<Vec<(PathBuf, Option<Arc<DomainList>>)>> = Mutex::new(Vec::new());
+
+/// Loads the list at `path`, or returns it if it was loaded before
+///
+/// Returns `None` with a warning if the file can't be read, which is remembered as well.
+#[must_use]
+pub fn load_cached(path: &Path) -> Option<Arc<DomainList>> {
+    // A panic while holding the lock can't leave the cache half-written
+    let mut loaded = LOADED
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((_, list)) = loaded.iter().find(|(loaded_path, _)| loaded_path == path) {
+        return list.clone();
+    }
+    let list = DomainList::load(path)
+        .inspect_err(|err| warn!("Failed to read domain list {:?}: {}", path, err))
+        .ok()
+        .map(Arc::new);
+    loaded.push((path.to_path_buf(), list.clone()));
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DomainList;
+
+    const LIST: &str = "
+# Work
+example.com
+*.corp.com   # Only subdomains
+.internal.net
+bücher.DE
+*.münchen.de
+example.*
+";
+
+    #[test]
+    fn suffix_tree() {
+        let list = DomainList::parse(LIST);
+        assert!(list.contains("example.com"));
+        assert!(list.contains("EXAMPLE.com."));
+        assert!(!list.contains("www.example.com"));
+        assert!(list.contains("a.b.corp.com"));
+        assert!(!list.contains("corp.com"));
+        assert!(list.contains("internal.net"));
+        assert!(list.contains("deep.sub.internal.net"));
+        assert!(!list.contains("notinternal.net"));
+        assert!(!list.contains("com"));
+        assert!(!list.contains(""));
+    }
+
+    #[test]
+    fn globs_and_idn() {
+        let list = DomainList::parse(LIST);
+        assert!(list.contains("example.org"));
+        assert!(!list.contains("www.example.org"));
+        // URL hosts are Punycode
+        assert!(list.contains("xn--bcher-kva.de"));
+        assert!(list.contains("rathaus.xn--mnchen-3ya.de"));
+        assert!(!list.contains("xn--mnchen-3ya.de"));
+    }
+}
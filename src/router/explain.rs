@@ -0,0 +1,546 @@
+use crate::app::{AppConfig, CommandArguments};
+use crate::profile_loader::Installation;
+use crate::router::handler::find_handler;
+use crate::router::opener::Opener;
+use crate::router::script::{ScriptAction, ScriptDecision};
+use crate::router::{route, RouteContext};
+use crate::uri::homograph::HomographWarning;
+use crate::uri::policy::{Refusal, SchemeAction, Verdict};
+use crate::uri::preprocess;
+use crate::uri::rewrite::RewriteStep;
+use chrono::NaiveDateTime;
+use log::{error, warn};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::process::Command;
+use url::Url;
+
+/// How a URI would be routed, without anything having been opened
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RouteExplanation {
+    pub uri: String,
+    /// The redirectors the URI was unwrapped from, outermost first
+    pub wrappers: Vec<String>,
+    /// The URI after unwrapping, before rewriting
+    pub unwrapped_uri: String,
+    pub rewrites: Vec<RewriteStep>,
+    /// The URI after unwrapping, rewriting, the script and sanitising, which was routed
+    pub routed_uri: String,
+    /// `None` if no script is configured
+    pub script: Option<ScriptDecision>,
+    /// `None` if the URI couldn't be parsed
+    pub scheme_action: Option<SchemeAction>,
+    /// Set if the scheme policy refuses the URI, nothing would be opened then
+    pub refusal: Option<Refusal>,
+    /// About internationalised and lookalike hosts
+    pub homograph_warnings: Vec<String>,
+    /// Whether the URI would be opened in the quarantine target because of a suspicious host
+    pub quarantined: bool,
+    pub opener: Option<Opener>,
+    /// The local time conditions were checked against
+    pub time: NaiveDateTime,
+    /// The scheme handler opening the URI instead of a browser
+    pub handler_index: Option<usize>,
+    /// `None` if no rule matched and the dialog would be opened
+    pub rule_index: Option<usize>,
+    pub installation: Option<String>,
+    pub profile: Option<PathBuf>,
+    pub container: Option<String>,
+    /// Descriptions of the conditions of the matched rule
+    pub conditions: Vec<String>,
+    /// Tracking parameters removed from the URI
+    pub stripped_params: Vec<String>,
+    /// The program followed by its arguments, empty if the dialog would be opened
+    pub argv: Vec<String>,
+}
+
+impl Display for RouteExplanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "URI: {}", self.uri)?;
+        if !self.wrappers.is_empty() {
+            writeln!(f, "Unwrapped: {}", self.unwrapped_uri)?;
+        }
+        for step in &self.rewrites {
+            writeln!(f, "Rewrite {}: {}", step.rewrite_index, step.uri)?;
+        }
+        match &self.opener {
+            Some(Opener {
+                name,
+                path: Some(path),
+            }) => writeln!(f, "Opened by: {name} ({})", path.display())?,
+            Some(Opener { name, path: None }) => writeln!(f, "Opened by: {name}")?,
+            None => writeln!(f, "Opened by: unknown")?,
+        }
+        writeln!(f, "Time: {}", self.time.format("%a %Y-%m-%d %H:%M"))?;
+        if let Some(script) = &self.script {
+            if let Some(uri) = &script.uri {
+                writeln!(f, "Script rewrote: {uri}")?;
+            }
+            match &script.action {
+                ScriptAction::Continue => writeln!(f, "Script: left it to the rules")?,
+                ScriptAction::Ask => writeln!(f, "Script: ask")?,
+                ScriptAction::Route(target) => writeln!(
+                    f,
+                    "Script: route to `{}` with profile {}",
+                    target.installation,
+                    target.profile.display()
+                )?,
+            }
+        }
+        match self.scheme_action {
+            Some(SchemeAction::Confirm) if self.refusal.is_none() => {
+                writeln!(
+                    f,
+                    "Scheme policy: confirm, explained as if it was confirmed"
+                )?;
+            }
+            Some(scheme_action) => writeln!(f, "Scheme policy: {scheme_action}")?,
+            None => {}
+        }
+        let preprocessed_uri = self
+            .script
+            .as_ref()
+            .and_then(|script| script.uri.as_ref())
+            .or(self.rewrites.last().map(|step| &step.uri))
+            .unwrap_or(&self.unwrapped_uri);
+        if self.refusal.is_none() && self.routed_uri != *preprocessed_uri {
+            writeln!(f, "Sanitised: {}", self.routed_uri)?;
+        }
+        if let Some(refusal) = &self.refusal {
+            return writeln!(f, "Refused, the URI {refusal}");
+        }
+        for warning in &self.homograph_warnings {
+            writeln!(f, "{warning}")?;
+        }
+        if let Some(handler_index) = self.handler_index {
+            writeln!(f, "Handled by handler {handler_index}")?;
+            return writeln!(f, "Command: {:?}", self.argv);
+        }
+        let script_action = self.script.as_ref().map(|script| &script.action);
+        if self.quarantined {
+            writeln!(f, "Quarantined because of the suspicious host")?;
+        } else if script_action == Some(&ScriptAction::Ask) {
+            return writeln!(f, "The script asks, the dialog would be opened");
+        } else if matches!(script_action, Some(ScriptAction::Route(_))) && !self.argv.is_empty() {
+            writeln!(f, "Routed by the script")?;
+        } else {
+            let Some(rule_index) = self.rule_index else {
+                return writeln!(f, "No rule matched, the dialog would be opened");
+            };
+            writeln!(f, "Matched rule {rule_index}")?;
+            if !self.conditions.is_empty() {
+                writeln!(f, "Conditions: {}", self.conditions.join("; "))?;
+            }
+        }
+        if let Some(installation) = &self.installation {
+            writeln!(f, "Installation: {installation}")?;
+        }
+        if let Some(profile) = &self.profile {
+            writeln!(f, "Profile: {}", profile.display())?;
+        }
+        if let Some(container) = &self.container {
+            writeln!(f, "Container: {container}")?;
+        }
+        if !self.stripped_params.is_empty() {
+            writeln!(f, "Stripped: {}", self.stripped_params.join(", "))?;
+        }
+        writeln!(f, "Command: {:?}", self.argv)
+    }
+}
+
+/// What to do with a URI, decided by `plan`
+pub struct RoutePlan {
+    pub explanation: RouteExplanation,
+    /// What to launch, `None` if the URI is refused, opened by a scheme handler or the dialog
+    /// has to be opened for it
+    pub command_args: Option<CommandArguments>,
+}
+
+/// Routes the URI like opening it does, collecting what would be launched instead of
+/// launching it
+///
+/// URIs the scheme policy asks about are explained as if they were confirmed.
+#[must_use]
+pub fn explain(
+    uri: &str,
+    config: &AppConfig,
+    installations: &[Installation],
+    context: &RouteContext,
+) -> RouteExplanation {
+    plan(uri, config, installations, context, |_, _| true).explanation
+}
+
+/// Decides how the URI is opened: preprocessing it, running the script, the scheme policy,
+/// handlers, the homograph checks and finally the rules
+///
+/// `confirm` is asked whether to open URIs the scheme policy wants confirmed.
+pub fn plan(
+    uri: &str,
+    config: &AppConfig,
+    installations: &[Installation],
+    context: &RouteContext,
+    confirm: impl FnOnce(&str, &str) -> bool,
+) -> RoutePlan {
+    let preprocessed = preprocess(uri, config);
+    let mut explanation = RouteExplanation {
+        uri: uri.to_string(),
+        wrappers: preprocessed.wrappers,
+        unwrapped_uri: preprocessed.unwrapped_uri,
+        rewrites: preprocessed.rewrites,
+        routed_uri: preprocessed.uri,
+        script: None,
+        scheme_action: None,
+        refusal: None,
+        homograph_warnings: Vec::new(),
+        quarantined: false,
+        opener: context.opener.clone(),
+        time: context.now,
+        handler_index: None,
+        rule_index: None,
+        installation: None,
+        profile: None,
+        container: None,
+        conditions: Vec::new(),
+        stripped_params: Vec::new(),
+        argv: Vec::new(),
+    };
+    let command_args = decide(&mut explanation, config, installations, context, confirm);
+    RoutePlan {
+        explanation,
+        command_args,
+    }
+}
+
+/// Fills in the explanation past preprocessing, returning what to launch
+fn decide(
+    explanation: &mut RouteExplanation,
+    config: &AppConfig,
+    installations: &[Installation],
+    context: &RouteContext,
+    confirm: impl FnOnce(&str, &str) -> bool,
+) -> Option<CommandArguments> {
+    // Before validating, so URIs rewritten by the script are validated as well
+    if config.script.path.is_some() {
+        let decision = config
+            .script
+            .decide(&explanation.routed_uri, installations, context);
+        if let Some(uri) = &decision.uri {
+            explanation.routed_uri.clone_from(uri);
+        }
+        explanation.script = Some(decision);
+    }
+    let handler = find_handler(&explanation.routed_uri, &config.handlers);
+    explanation.scheme_action = Url::parse(&explanation.routed_uri)
+        .ok()
+        .map(|url| config.policy.action(url.scheme(), handler.is_some()));
+    match config
+        .policy
+        .validate(&explanation.routed_uri, handler.is_some())
+    {
+        Verdict::Allowed(uri) => explanation.routed_uri = uri,
+        Verdict::Confirm { uri, scheme } => {
+            if !confirm(&uri, &scheme) {
+                explanation.refusal = Some(Refusal::Declined { scheme });
+                return None;
+            }
+            explanation.routed_uri = uri;
+        }
+        Verdict::Refused(refusal) => {
+            explanation.refusal = Some(refusal);
+            return None;
+        }
+    }
+    // Kept whichever of the handlers, quarantine, script or rules decides
+    let homograph_warnings = config.homograph.check(&explanation.routed_uri);
+    explanation.homograph_warnings = homograph_warnings.iter().map(ToString::to_string).collect();
+    if let Some((handler_index, handler)) = handler {
+        explanation.handler_index = Some(handler_index);
+        explanation.argv = argv(&handler.create_command(&explanation.routed_uri));
+        return None;
+    }
+    if homograph_warnings
+        .iter()
+        .any(HomographWarning::is_suspicious)
+    {
+        warn!(
+            "Suspicious host in {}: {:?}",
+            explanation.routed_uri, homograph_warnings
+        );
+        if let Some(quarantine) = &config.homograph.quarantine {
+            if let Some(mut quarantine_args) =
+                quarantine.resolve(&explanation.routed_uri, installations)
+            {
+                explanation.stripped_params = config.tracking.apply(&mut quarantine_args, None);
+                explanation.quarantined = true;
+                explanation.installation = Some(quarantine.installation.clone());
+                explanation.profile = Some(quarantine_args.profile.profile_path.clone());
+                explanation.container.clone_from(&quarantine_args.container);
+                explanation.argv = argv(&quarantine_args.create_command());
+                return Some(quarantine_args);
+            }
+            error!("The quarantine target could not be found, falling back to routing");
+        }
+    }
+    match explanation.script.as_ref().map(|script| &script.action) {
+        Some(ScriptAction::Ask) => return None,
+        Some(ScriptAction::Route(target)) => {
+            if let Some(mut script_args) = target.resolve(&explanation.routed_uri, installations) {
+                explanation.stripped_params = config.tracking.apply(&mut script_args, None);
+                explanation.installation = Some(target.installation.clone());
+                explanation.profile = Some(script_args.profile.profile_path.clone());
+                explanation.container.clone_from(&script_args.container);
+                explanation.argv = argv(&script_args.create_command());
+                return Some(script_args);
+            }
+            warn!(
+                "The script routed to `{}` with profile {:?}, which could not be found",
+                target.installation, target.profile
+            );
+        }
+        Some(ScriptAction::Continue) | None => {}
+    }
+    let mut route_match = route(
+        &explanation.routed_uri,
+        &config.rules,
+        installations,
+        context,
+    )?;
+    let rule = &config.rules[route_match.rule_index];
+    explanation.stripped_params = config
+        .tracking
+        .apply(&mut route_match.command_args, rule.strip_tracking);
+    explanation.rule_index = Some(route_match.rule_index);
+    explanation.installation = Some(rule.target.installation.clone());
+    explanation.profile = Some(route_match.command_args.profile.profile_path.clone());
+    explanation
+        .container
+        .clone_from(&route_match.command_args.container);
+    explanation.conditions = rule.conditions.describe();
+    explanation.argv = argv(&route_match.command_args.create_command());
+    Some(route_match.command_args)
+}
+
+/// The program followed by its arguments
+fn argv(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explain, plan};
+    use crate::app::AppConfig;
+    use crate::profile_loader::Installation;
+    use crate::router::network::SystemProbe;
+    use crate::router::RouteContext;
+    use crate::uri::policy::Refusal;
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    const CONFIG: &str = r#"
+cfg_version = 0
+
+[[rules]]
+host = "*.corp.com"
+weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri"]
+time = "09:00-17:30"
+installation = "Firefox"
+profile = "/work"
+
+[[rules]]
+host = "*.corp.com"
+installation = "Firefox"
+profile = "/personal"
+strip_tracking = false
+
+[tracking]
+enabled = true
+"#;
+
+    fn installations() -> Vec<Installation> {
+        #[derive(Deserialize)]
+        struct Cache {
+            installations: Vec<Installation>,
+        }
+        let cache: Cache = toml::from_str(
+            r#"
+[[installations]]
+browser_type = "Firefox"
+exe_path = "/bin/firefox"
+symlinks = []
+profiles = [
+    { name = "Work", profile_path = "/work" },
+    { name = "Personal", profile_path = "/personal" },
+]
+
+[installations.preferred]
+"#,
+        )
+        .unwrap();
+        cache.installations
+    }
+
+    /// The context on 2025-03-03, a Monday, at the time
+    fn at(hour: u32, minute: u32) -> RouteContext {
+        RouteContext {
+            opener: None,
+            now: NaiveDate::from_ymd_opt(2025, 3, 3)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+            network: Rc::new(SystemProbe::with_root("/nonexistent".into())),
+            env: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn explains_as_json() {
+        let config: AppConfig = toml::from_str(CONFIG).unwrap();
+        let explanation = explain(
+            "https://a.corp.com/x?id=1&utm_source=mail",
+            &config,
+            &installations(),
+            &at(10, 0),
+        );
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert_eq!(json["rule_index"], json!(0));
+        assert_eq!(json["profile"], json!("/work"));
+        assert_eq!(json["time"], json!("2025-03-03T10:00:00"));
+        assert_eq!(json["stripped_params"], json!(["utm_source"]));
+        assert_eq!(
+            json["conditions"],
+            json!(["on Mon, Tue, Wed, Thu, Fri", "between 09:00 and 17:30"])
+        );
+        assert_eq!(
+            json["argv"],
+            json!([
+                "/bin/firefox",
+                "--profile",
+                "/work",
+                "-url",
+                "https://a.corp.com/x?id=1"
+            ])
+        );
+    }
+
+    #[test]
+    fn the_clock_decides_the_rule() {
+        let config: AppConfig = toml::from_str(CONFIG).unwrap();
+        let evening = explain(
+            "https://a.corp.com/x?utm_source=mail",
+            &config,
+            &installations(),
+            &at(18, 0),
+        );
+        assert_eq!(evening.rule_index, Some(1));
+        assert!(evening.stripped_params.is_empty());
+        assert_eq!(
+            evening.argv.last().map(String::as_str),
+            Some("https://a.corp.com/x?utm_source=mail")
+        );
+        let unrouted = explain(
+            "https://example.com/",
+            &config,
+            &installations(),
+            &at(10, 0),
+        );
+        assert_eq!(unrouted.rule_index, None);
+        assert!(unrouted.argv.is_empty());
+        assert!(unrouted.to_string().contains("the dialog would be opened"));
+    }
+
+    #[test]
+    fn plan_asks_for_confirmation() {
+        let config: AppConfig = toml::from_str(CONFIG).unwrap();
+        let installations = installations();
+        let declined = plan(
+            "foo:bar",
+            &config,
+            &installations,
+            &at(10, 0),
+            |uri, scheme| {
+                assert_eq!((uri, scheme), ("foo:bar", "foo"));
+                false
+            },
+        );
+        assert_eq!(
+            declined.explanation.refusal,
+            Some(Refusal::Declined {
+                scheme: "foo".to_string()
+            })
+        );
+        assert!(declined.command_args.is_none());
+        let explained = explain("foo:bar", &config, &installations, &at(10, 0));
+        assert_eq!(explained.refusal, None);
+        assert!(explained
+            .to_string()
+            .contains("explained as if it was confirmed"));
+        let routed = plan(
+            "https://a.corp.com/",
+            &config,
+            &installations,
+            &at(10, 0),
+            |_, _| panic!("Web URIs aren't confirmed"),
+        );
+        assert_eq!(
+            routed
+                .command_args
+                .map(|args| args.profile.profile_path.clone()),
+            Some("/work".into())
+        );
+    }
+
+    #[test]
+    fn homograph_warnings_are_kept_by_every_branch() {
+        let config: AppConfig = toml::from_str(&format!(
+            r#"{CONFIG}
+[homograph]
+protected_domains = ["paypal.com"]
+
+[homograph.quarantine]
+installation = "Firefox"
+profile = "/personal"
+
+[[handlers]]
+scheme = "irc"
+program = "/bin/irc"
+"#
+        ))
+        .unwrap();
+        let installations = installations();
+        let quarantined = explain(
+            "https://paypai.com/x?utm_source=mail",
+            &config,
+            &installations,
+            &at(10, 0),
+        );
+        assert!(quarantined.quarantined);
+        assert_eq!(quarantined.stripped_params, vec!["utm_source"]);
+        assert_eq!(
+            quarantined.argv.last().map(String::as_str),
+            Some("https://paypai.com/x")
+        );
+        assert_eq!(quarantined.homograph_warnings.len(), 1);
+        // Internationalised domains aren't suspicious, the rules still route them
+        let routed = explain(
+            "https://a.bücher.corp.com/",
+            &config,
+            &installations,
+            &at(10, 0),
+        );
+        assert!(!routed.quarantined);
+        assert_eq!(routed.rule_index, Some(0));
+        assert_eq!(routed.homograph_warnings.len(), 1);
+        assert!(routed.to_string().contains("internationalised domain"));
+        let handled = explain("irc://paypai.com/chan", &config, &installations, &at(10, 0));
+        assert_eq!(handled.handler_index, Some(0));
+        assert!(!handled.quarantined);
+        assert_eq!(handled.homograph_warnings.len(), 1);
+    }
+}
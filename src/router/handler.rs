@@ -0,0 +1,104 @@
+use crate::app::detach;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use url::Url;
+
+/// A non-browser program opening every URI of a scheme, like a mail client for `mailto:`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemeHandler {
+    /// Compared case-insensitively, without the trailing `:`
+    pub scheme: String,
+    pub program: PathBuf,
+    /// Argument templates, `{uri}` is replaced by the whole URI and `{target}` by everything
+    /// after the `scheme:`. Defaults to just the URI.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl SchemeHandler {
+    #[must_use]
+    pub fn create_command(&self, uri: &str) -> Command {
+        let target = uri.split_once(':').map_or("", |(_, target)| target);
+        let mut command = Command::new(&self.program);
+        if self.args.is_empty() {
+            command.arg(uri);
+        } else {
+            command.args(self.args.iter().map(|arg| fill_template(arg, uri, target)));
+        }
+        detach(&mut command);
+        command
+    }
+}
+
+/// Replaces `{uri}` and `{target}` in one pass, so placeholders inside the URI are kept as they
+/// are
+fn fill_template(template: &str, uri: &str, target: &str) -> String {
+    let mut filled = String::with_capacity(template.len() + uri.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{uri}") {
+            filled.push_str(uri);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{target}") {
+            filled.push_str(target);
+            rest = after;
+        } else {
+            filled.push('{');
+            rest = &rest[1..];
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Finds the handler for the scheme of the URI, returning its index in `handlers` with it
+#[must_use]
+pub fn find_handler<'a>(
+    uri: &str,
+    handlers: &'a [SchemeHandler],
+) -> Option<(usize, &'a SchemeHandler)> {
+    let url = Url::parse(uri).ok()?;
+    handlers.iter().enumerate().find(|(_, handler)| {
+        url.scheme()
+            .eq_ignore_ascii_case(handler.scheme.trim_end_matches(':'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_template, find_handler, SchemeHandler};
+
+    #[test]
+    fn placeholders_are_filled_once() {
+        assert_eq!(
+            fill_template("--to={target} {uri}", "mailto:a@b.c", "a@b.c"),
+            "--to=a@b.c mailto:a@b.c"
+        );
+        // A `{target}` in the URI itself stays as it is
+        assert_eq!(
+            fill_template("{uri}", "https://x.com/{target}", "//x.com/{target}"),
+            "https://x.com/{target}"
+        );
+        assert_eq!(fill_template("{{uri}}{", "a:b", "b"), "{a:b}{");
+    }
+
+    #[test]
+    fn handler_by_scheme() {
+        let handlers = vec![SchemeHandler {
+            scheme: "MailTo:".to_string(),
+            program: "thunderbird".into(),
+            args: vec!["-compose".to_string(), "to={target}".to_string()],
+        }];
+        let (index, handler) = find_handler("mailto:a@b.c", &handlers).unwrap();
+        assert_eq!(index, 0);
+        let command = handler.create_command("mailto:a@b.c");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["-compose", "to=a@b.c"]
+        );
+        assert!(find_handler("https://a.b", &handlers).is_none());
+    }
+}
@@ -0,0 +1,717 @@
+use crate::profile_loader::{Browsers, Installation};
+use crate::router::conditions::Conditions;
+use crate::router::{Matcher, Rule, RuleTarget};
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+/// The formats rules can be imported from, Choosy and Browserosaurus rules aren't supported
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    /// A Finicky `.finicky.js`, only its `handlers` made of literals are imported
+    Finicky,
+    /// plinks' own JSON array of `{ "match": ..., "browser": ..., "profile": ... }` objects,
+    /// with `match` a URL wildcard or a list of them, not a format of another picker
+    Json,
+}
+
+/// A handler of another browser picker, reduced to what plinks can represent
+#[derive(Debug, Clone, PartialEq)]
+struct ForeignRule {
+    matchers: Vec<Result<Matcher, String>>,
+    browser: Result<ForeignBrowser, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ForeignBrowser {
+    name: String,
+    profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unmapped {
+    /// Index of the handler in the imported file
+    pub handler_index: usize,
+    pub reason: String,
+}
+
+impl Display for Unmapped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handler {}: {}", self.handler_index, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImportReport {
+    pub rules: Vec<Rule>,
+    pub unmapped: Vec<Unmapped>,
+}
+
+/// Converts the rules of another browser picker into plinks rules
+///
+/// Browsers are resolved to installations by their name first, then by their browser type.
+/// A handler without a profile can only be mapped to an installation with a single profile.
+///
+/// # Errors
+///
+/// If the file as a whole can't be parsed. Handlers which can't be mapped are reported in
+/// `ImportReport::unmapped` instead.
+pub fn import_rules(
+    format: ImportFormat,
+    source: &str,
+    installations: &[Installation],
+) -> Result<ImportReport, String> {
+    let foreign_rules = match format {
+        ImportFormat::Finicky => parse_finicky(source)?,
+        ImportFormat::Json => parse_json(source)?,
+    };
+    let mut report = ImportReport::default();
+    for (handler_index, foreign_rule) in foreign_rules.into_iter().enumerate() {
+        let target = foreign_rule
+            .browser
+            .and_then(|browser| resolve_target(&browser, installations));
+        let target = match target {
+            Ok(target) => target,
+            Err(reason) => {
+                report.unmapped.push(Unmapped {
+                    handler_index,
+                    reason,
+                });
+                continue;
+            }
+        };
+        for matcher in foreign_rule.matchers {
+            match matcher {
+                Ok(matcher) => report.rules.push(Rule {
+                    matcher,
+                    conditions: Conditions::default(),
+                    target: target.clone(),
+                    strip_tracking: None,
+                }),
+                Err(reason) => report.unmapped.push(Unmapped {
+                    handler_index,
+                    reason,
+                }),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn resolve_target(
+    browser: &ForeignBrowser,
+    installations: &[Installation],
+) -> Result<RuleTarget, String> {
+    // Installations named like the browser take precedence, e.g. renamed ones
+    let installation = installations
+        .iter()
+        .find(|i| i.get_name().eq_ignore_ascii_case(&browser.name))
+        .or_else(|| {
+            installations
+                .iter()
+                .find(|i| is_browser_named(&i.browser_type, &browser.name))
+        })
+        .ok_or_else(|| format!("No installation found for the browser `{}`", browser.name))?;
+    let profile = match &browser.profile {
+        Some(profile_name) => installation
+            .profiles
+            .iter()
+            .find(|p| {
+                // Chromium profiles are also known by their directory, like `Profile 1`
+                p.to_string().eq_ignore_ascii_case(profile_name)
+                    || p.profile_path
+                        .file_name()
+                        .is_some_and(|directory| directory == profile_name.as_str())
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No profile named `{}` found in {}",
+                    profile_name,
+                    installation.get_name()
+                )
+            })?,
+        None => match installation.profiles.as_slice() {
+            [profile] => profile,
+            _ => {
+                return Err(format!(
+                    "No profile given and {} has multiple profiles",
+                    installation.get_name()
+                ))
+            }
+        },
+    };
+    Ok(RuleTarget {
+        installation: installation.get_name().to_string(),
+        profile: profile.profile_path.clone(),
+        executable: None,
+        container: None,
+    })
+}
+
+/// Whether other pickers would call the browser type by that name or bundle id
+fn is_browser_named(browser_type: &Browsers, name: &str) -> bool {
+    match name.to_lowercase().as_str() {
+        "firefox" | "org.mozilla.firefox" => {
+            matches!(browser_type, Browsers::Firefox | Browsers::FirefoxPortable)
+        }
+        "firefox nightly" | "org.mozilla.nightly" => browser_type == &Browsers::FirefoxNightly,
+        "firefox beta" => browser_type == &Browsers::FirefoxBeta,
+        "firefox developer edition"
+        | "firefox developer"
+        | "org.mozilla.firefoxdeveloperedition" => browser_type == &Browsers::FirefoxDeveloper,
+        "librewolf" | "io.gitlab.librewolf-community" => {
+            matches!(
+                browser_type,
+                Browsers::Librewolf | Browsers::LibrewolfPortable
+            )
+        }
+        "google chrome" | "chrome" | "com.google.chrome" => browser_type == &Browsers::Chrome,
+        "chromium" | "org.chromium.chromium" => browser_type == &Browsers::Chromium,
+        "brave browser" | "brave" | "com.brave.browser" => browser_type == &Browsers::Brave,
+        "vivaldi" | "com.vivaldi.vivaldi" => browser_type == &Browsers::Vivaldi,
+        "microsoft edge" | "edge" | "com.microsoft.edgemac" => browser_type == &Browsers::Edge,
+        _ => false,
+    }
+}
+
+/// Turns a wildcard pattern over the whole URL into a matcher
+///
+/// Patterns without a scheme like `example.com/*` match any scheme, as they do in Finicky.
+fn url_glob_matcher(glob: &str) -> Matcher {
+    let mut regex = String::from("^");
+    if !glob.contains("://") {
+        regex.push_str("(?:[a-zA-Z][a-zA-Z0-9+.-]*://)?");
+    }
+    for (i, part) in glob.split('*').enumerate() {
+        if i > 0 {
+            regex.push_str(".*");
+        }
+        regex.push_str(&regex::escape(part));
+    }
+    regex.push('$');
+    Matcher {
+        regex: Some(regex),
+        ..Matcher::default()
+    }
+}
+
+fn parse_json(source: &str) -> Result<Vec<ForeignRule>, String> {
+    #[derive(Deserialize)]
+    struct JsonRule {
+        #[serde(rename = "match")]
+        match_: StringOrList,
+        browser: String,
+        profile: Option<String>,
+    }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    let json_rules: Vec<JsonRule> =
+        serde_json::from_str(source).map_err(|err| format!("Invalid rule file: {err}"))?;
+    Ok(json_rules
+        .into_iter()
+        .map(|rule| ForeignRule {
+            matchers: match rule.match_ {
+                StringOrList::String(glob) => vec![Ok(url_glob_matcher(&glob))],
+                StringOrList::List(globs) => globs
+                    .iter()
+                    .map(|glob| Ok(url_glob_matcher(glob)))
+                    .collect(),
+            },
+            browser: Ok(ForeignBrowser {
+                name: rule.browser,
+                profile: rule.profile,
+            }),
+        })
+        .collect())
+}
+
+fn parse_finicky(source: &str) -> Result<Vec<ForeignRule>, String> {
+    let mut parser = JsParser {
+        src: source,
+        pos: 0,
+    };
+    let JsValue::Array(handlers) = parser.find_property("handlers")? else {
+        return Err("`handlers` is not an array".to_string());
+    };
+    Ok(handlers
+        .iter()
+        .map(|handler| {
+            let JsValue::Object(properties) = handler else {
+                return ForeignRule {
+                    matchers: Vec::new(),
+                    browser: Err("Handler is not an object literal".to_string()),
+                };
+            };
+            let property = |key: &str| properties.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            ForeignRule {
+                matchers: match property("match") {
+                    Some(value) => finicky_matchers(value),
+                    None => vec![Err("Handler has no `match`".to_string())],
+                },
+                browser: match property("browser") {
+                    Some(value) => finicky_browser(value),
+                    None => Err("Handler has no `browser`".to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+fn finicky_matchers(value: &JsValue) -> Vec<Result<Matcher, String>> {
+    match value {
+        JsValue::Str(glob) => vec![Ok(url_glob_matcher(glob))],
+        JsValue::Regex(regex) => vec![Regex::new(regex)
+            .map(|_| Matcher {
+                regex: Some(regex.clone()),
+                ..Matcher::default()
+            })
+            .map_err(|err| format!("Unsupported regex /{regex}/: {err}"))],
+        JsValue::Array(values) => values.iter().flat_map(finicky_matchers).collect(),
+        JsValue::Call(function, args)
+            if function == "finicky.matchHostnames" || function == "finicky.matchDomains" =>
+        {
+            let hosts = match args.as_slice() {
+                [JsValue::Array(hosts)] => hosts.as_slice(),
+                hosts => hosts,
+            };
+            hosts
+                .iter()
+                .map(|host| match host {
+                    JsValue::Str(host) => Ok(Matcher {
+                        host: Some(host.clone()),
+                        ..Matcher::default()
+                    }),
+                    other => Err(format!(
+                        "Only plain hostnames can be imported from `{function}`, not {other}"
+                    )),
+                })
+                .collect()
+        }
+        other => vec![Err(format!("Unsupported matcher {other}"))],
+    }
+}
+
+fn finicky_browser(value: &JsValue) -> Result<ForeignBrowser, String> {
+    match value {
+        JsValue::Str(name) => Ok(ForeignBrowser {
+            name: name.clone(),
+            profile: None,
+        }),
+        JsValue::Object(properties) => {
+            let property = |key: &str| properties.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let Some(JsValue::Str(name)) = property("name") else {
+                return Err("Browser object has no `name` string".to_string());
+            };
+            let profile = match property("profile") {
+                Some(JsValue::Str(profile)) => Some(profile.clone()),
+                Some(other) => return Err(format!("Unsupported browser profile {other}")),
+                None => None,
+            };
+            Ok(ForeignBrowser {
+                name: name.clone(),
+                profile,
+            })
+        }
+        other => Err(format!("Unsupported browser {other}")),
+    }
+}
+
+/// The subset of JavaScript values found in Finicky configs that can be imported
+#[derive(Debug, Clone, PartialEq)]
+enum JsValue {
+    Str(String),
+    /// The source of a regex literal, with the `i` flag turned into `(?i)`
+    Regex(String),
+    Array(Vec<JsValue>),
+    Object(Vec<(String, JsValue)>),
+    /// A call like `finicky.matchHostnames([...])`, with the dotted path of the function
+    Call(String, Vec<JsValue>),
+    /// Anything else, e.g. functions, kept as source for the report
+    Other(String),
+}
+
+impl Display for JsValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsValue::Str(string) => write!(f, "{string:?}"),
+            JsValue::Regex(regex) => write!(f, "/{regex}/"),
+            JsValue::Array(_) => write!(f, "array"),
+            JsValue::Object(_) => write!(f, "object"),
+            JsValue::Call(function, _) => write!(f, "`{function}(...)`"),
+            JsValue::Other(source) => {
+                let source: String = source.chars().take(40).collect();
+                write!(
+                    f,
+                    "`{}`",
+                    source.split_whitespace().collect::<Vec<_>>().join(" ")
+                )
+            }
+        }
+    }
+}
+
+struct JsParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl JsParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_trivia();
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!(
+                "Expected `{expected}` but found `{c}` at {}",
+                self.pos
+            )),
+            None => Err(format!("Expected `{expected}` but the file ended")),
+        }
+    }
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                self.pos += trimmed.find("*/").map_or(trimmed.len(), |end| end + 2);
+            } else {
+                return;
+            }
+        }
+    }
+    /// Parses the value of the first `key:` property of an object literal in the source
+    ///
+    /// Strings, comments and regex literals are skipped, so the key isn't found inside them.
+    fn find_property(&mut self, key: &str) -> Result<JsValue, String> {
+        self.pos = 0;
+        // The last character outside of trivia, to tell keys and regexes apart
+        let mut previous = None;
+        loop {
+            self.skip_trivia();
+            let Some(c) = self.peek() else {
+                return Err(format!("No `{key}` found"));
+            };
+            let name = match c {
+                '"' | '\'' | '`' => self.parse_string(c).ok(),
+                '/' if previous.is_none_or(|p| "(,=:[!&|?{};".contains(p)) => {
+                    let _ = self.parse_regex();
+                    None
+                }
+                c if c.is_alphabetic() || c == '_' || c == '$' => self.parse_identifier(),
+                c => {
+                    self.bump();
+                    previous = Some(c);
+                    continue;
+                }
+            };
+            let is_key = matches!(previous, Some('{' | ','));
+            previous = Some(c);
+            if is_key && name.as_deref() == Some(key) {
+                self.skip_trivia();
+                if self.peek() == Some(':') {
+                    self.bump();
+                    return self.parse_value();
+                }
+            }
+        }
+    }
+    fn parse_value(&mut self) -> Result<JsValue, String> {
+        self.skip_trivia();
+        match self.peek() {
+            Some(quote @ ('"' | '\'' | '`')) => self.parse_string(quote).map(JsValue::Str),
+            Some('/') => self.parse_regex(),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(_) => self.parse_expression(),
+            None => Err("Expected a value but the file ended".to_string()),
+        }
+    }
+    fn parse_string(&mut self, quote: char) -> Result<String, String> {
+        self.bump();
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some(c) => string.push(c),
+                    None => break,
+                },
+                Some('$') if quote == '`' && self.peek() == Some('{') => {
+                    return Err("Template literals with substitutions are unsupported".to_string())
+                }
+                Some(c) if c == quote => return Ok(string),
+                Some(c) => string.push(c),
+                None => break,
+            }
+        }
+        Err("Unterminated string".to_string())
+    }
+    fn parse_regex(&mut self) -> Result<JsValue, String> {
+        self.bump();
+        let mut regex = String::new();
+        let mut in_class = false;
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    regex.push('\\');
+                    regex.extend(self.bump());
+                }
+                Some('[') => {
+                    in_class = true;
+                    regex.push('[');
+                }
+                Some(']') => {
+                    in_class = false;
+                    regex.push(']');
+                }
+                Some('/') if !in_class => break,
+                Some(c) => regex.push(c),
+                None => return Err("Unterminated regex".to_string()),
+            }
+        }
+        let mut flags = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+            self.bump();
+            flags.push(c);
+        }
+        if flags.contains('i') {
+            regex.insert_str(0, "(?i)");
+        }
+        Ok(JsValue::Regex(regex))
+    }
+    fn parse_array(&mut self) -> Result<JsValue, String> {
+        self.bump();
+        let mut values = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(JsValue::Array(values));
+            }
+            values.push(self.parse_value()?);
+            self.skip_trivia();
+            if self.peek() == Some(',') {
+                self.bump();
+            } else {
+                self.expect(']')?;
+                return Ok(JsValue::Array(values));
+            }
+        }
+    }
+    fn parse_object(&mut self) -> Result<JsValue, String> {
+        self.bump();
+        let mut properties = Vec::new();
+        loop {
+            self.skip_trivia();
+            let key = match self.peek() {
+                Some('}') => {
+                    self.bump();
+                    return Ok(JsValue::Object(properties));
+                }
+                Some(quote @ ('"' | '\'')) => Some(self.parse_string(quote)?),
+                _ => self.parse_identifier(),
+            };
+            self.skip_trivia();
+            match key {
+                Some(key) if self.peek() == Some(':') => {
+                    self.bump();
+                    properties.push((key, self.parse_value()?));
+                }
+                // Methods, spreads and shorthands can't be imported anyway
+                _ => {
+                    self.skip_expression();
+                }
+            }
+            self.skip_trivia();
+            if self.peek() == Some(',') {
+                self.bump();
+            } else {
+                self.expect('}')?;
+                return Ok(JsValue::Object(properties));
+            }
+        }
+    }
+    fn parse_identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.')
+        {
+            self.bump();
+        }
+        (self.pos > start).then(|| self.src[start..self.pos].to_string())
+    }
+    /// Parses calls of plain functions, everything else ends up as `JsValue::Other`
+    fn parse_expression(&mut self) -> Result<JsValue, String> {
+        let start = self.pos;
+        if let Some(function) = self.parse_identifier() {
+            self.skip_trivia();
+            if self.peek() == Some('(') {
+                self.bump();
+                let mut args = Vec::new();
+                loop {
+                    self.skip_trivia();
+                    if self.peek() == Some(')') {
+                        self.bump();
+                        break;
+                    }
+                    args.push(self.parse_value()?);
+                    self.skip_trivia();
+                    if self.peek() == Some(',') {
+                        self.bump();
+                    } else {
+                        self.expect(')')?;
+                        break;
+                    }
+                }
+                self.skip_trivia();
+                if matches!(self.peek(), Some(',' | ']' | '}' | ')') | None) {
+                    return Ok(JsValue::Call(function, args));
+                }
+            }
+        }
+        self.pos = start;
+        Ok(JsValue::Other(self.skip_expression().to_string()))
+    }
+    /// Skips to the end of the current expression, returning its source
+    fn skip_expression(&mut self) -> &str {
+        let start = self.pos;
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                '"' | '\'' | '`' => {
+                    // An unterminated string ends the expression at the end of the source
+                    let _ = self.parse_string(c);
+                    continue;
+                }
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' if depth == 0 => break,
+                ')' | ']' | '}' => depth -= 1,
+                ',' if depth == 0 => break,
+                _ => {}
+            }
+            self.bump();
+        }
+        self.src[start..self.pos].trim_end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import_rules, parse_finicky, ImportFormat, JsParser, JsValue};
+    use crate::profile_loader::Installation;
+    use serde::Deserialize;
+
+    const CONFIG: &str = r#"
+// The handlers: below aren't the ones of the comment
+module.exports = {
+  defaultBrowser: "Safari",
+  rewrite: [{ match: "x", url: "handlers: []" }],
+  "handlers": [
+    {
+      match: ["example.com/*", /^https:\/\/[a-z]+\.test\//i],
+      browser: "Firefox",
+    },
+    {
+      match: finicky.matchHostnames(["work.com", /work/]),
+      browser: { name: "Google Chrome", profile: "Profile 1" },
+    },
+    { match: /(unclosed/, browser: "Firefox" },
+    { match: ({ url }) => url.host.endsWith(".org"), browser: "Firefox" },
+  ],
+};
+"#;
+
+    #[test]
+    fn handlers_is_a_property_key() {
+        let mut parser = JsParser {
+            src: "const myhandlers = 1; /* handlers: 2 */ x = { 'a': `handlers:`, handlers: [3] }",
+            pos: 0,
+        };
+        assert_eq!(
+            parser.find_property("handlers"),
+            Ok(JsValue::Array(vec![JsValue::Other("3".to_string())]))
+        );
+        let mut parser = JsParser {
+            src: "x = { a: /handlers: [1]/, b: 'handlers' }",
+            pos: 0,
+        };
+        assert!(parser.find_property("handlers").is_err());
+    }
+
+    #[test]
+    fn finicky_handlers() {
+        let rules = parse_finicky(CONFIG).unwrap();
+        assert_eq!(rules.len(), 4);
+        let first: Vec<_> = rules[0]
+            .matchers
+            .iter()
+            .map(|m| m.as_ref().unwrap())
+            .collect();
+        assert_eq!(
+            first[0].regex.as_deref(),
+            Some(r"^(?:[a-zA-Z][a-zA-Z0-9+.-]*://)?example\.com/.*$")
+        );
+        assert_eq!(
+            first[1].regex.as_deref(),
+            Some(r"(?i)^https:\/\/[a-z]+\.test\/")
+        );
+        assert_eq!(rules[0].browser.as_ref().unwrap().name, "Firefox");
+        assert_eq!(
+            rules[1].matchers[0].as_ref().unwrap().host.as_deref(),
+            Some("work.com")
+        );
+        assert!(rules[1].matchers[1].is_err());
+        let chrome = rules[1].browser.as_ref().unwrap();
+        assert_eq!(
+            (chrome.name.as_str(), chrome.profile.as_deref()),
+            ("Google Chrome", Some("Profile 1"))
+        );
+        assert!(rules[2].matchers[0].is_err());
+        assert!(rules[3].matchers[0].is_err());
+    }
+
+    #[test]
+    fn unmapped_handlers_are_reported() {
+        #[derive(Deserialize)]
+        struct Cache {
+            installations: Vec<Installation>,
+        }
+        let cache: Cache = toml::from_str(
+            r#"
+[[installations]]
+browser_type = "Firefox"
+exe_path = "/bin/firefox"
+symlinks = []
+profiles = [{ name = "Work", profile_path = "/p" }]
+
+[installations.preferred]
+"#,
+        )
+        .unwrap();
+        let report = import_rules(ImportFormat::Finicky, CONFIG, &cache.installations).unwrap();
+        assert_eq!(report.rules.len(), 2);
+        assert!(report
+            .rules
+            .iter()
+            .all(|rule| rule.target.profile.to_str() == Some("/p")));
+        let unmapped: Vec<usize> = report.unmapped.iter().map(|u| u.handler_index).collect();
+        assert_eq!(unmapped, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,468 @@
+pub mod check;
+pub mod conditions;
+pub mod domain_list;
+pub mod explain;
+pub mod handler;
+pub mod import;
+pub mod network;
+pub mod opener;
+pub mod script;
+
+use crate::app::CommandArguments;
+use crate::profile_loader::Installation;
+use crate::router::conditions::Conditions;
+use crate::router::network::{NetworkProbe, SystemProbe};
+use crate::router::opener::Opener;
+use chrono::{Local, NaiveDateTime};
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use url::Url;
+
+/// The part of a `Rule` deciding whether it applies to a URI.
+///
+/// Every field that is set has to match, unset fields match anything. A matcher without any
+/// fields set therefore matches every URI, which is useful as a catch-all last rule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Matcher {
+    /// Compared case-insensitively, without the trailing `:`
+    pub scheme: Option<String>,
+    /// Glob over the host, `*` matches any amount of characters and `?` exactly one
+    pub host: Option<String>,
+    /// Path to a file listing hosts, one domain or wildcard per line, see `DomainList`
+    pub domain_list: Option<PathBuf>,
+    pub path_prefix: Option<String>,
+    /// File extension of the last path segment, without the `.` and compared case-insensitively.
+    /// The query and fragment aren't part of the path, `a.pdf?page=2` has the extension `pdf`.
+    pub extension: Option<String>,
+    /// Matched against the whole URI
+    pub regex: Option<String>,
+    /// Glob over the executable name of the application the URI was opened from
+    pub opener_name: Option<String>,
+    /// Glob over the executable path of the application the URI was opened from
+    pub opener_path: Option<String>,
+    #[serde(skip)]
+    compiled_regex: CompiledRegex,
+}
+
+/// `Matcher::regex` compiled on its first use, `None` if it is invalid
+///
+/// Left out of comparisons, it only ever follows the regex's source.
+#[derive(Debug, Clone, Default)]
+struct CompiledRegex(OnceCell<Option<Regex>>);
+
+impl PartialEq for CompiledRegex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Matcher {
+    /// Checks the URI against every set field of the matcher
+    ///
+    /// `parsed` is the URI parsed as an URL, if it could be parsed. URIs which can't be parsed
+    /// only ever match a matcher which has no `scheme`, `host`, `domain_list`, `path_prefix` or
+    /// `extension` set. Domain lists which can't be read match nothing.
+    #[must_use]
+    pub fn matches(&self, uri: &str, parsed: Option<&Url>, context: &RouteContext) -> bool {
+        if self.scheme.is_some()
+            || self.host.is_some()
+            || self.domain_list.is_some()
+            || self.path_prefix.is_some()
+            || self.extension.is_some()
+        {
+            let Some(url) = parsed else {
+                return false;
+            };
+            if let Some(scheme) = &self.scheme {
+                if !url
+                    .scheme()
+                    .eq_ignore_ascii_case(scheme.trim_end_matches(':'))
+                {
+                    return false;
+                }
+            }
+            if let Some(host) = &self.host {
+                if !url
+                    .host_str()
+                    .is_some_and(|url_host| glob_match(&host.to_lowercase(), url_host))
+                {
+                    return false;
+                }
+            }
+            if let Some(domain_list) = &self.domain_list {
+                if !url.host_str().is_some_and(|url_host| {
+                    domain_list::load_cached(domain_list)
+                        .is_some_and(|list| list.contains(url_host))
+                }) {
+                    return false;
+                }
+            }
+            if let Some(path_prefix) = &self.path_prefix {
+                if !url.path().starts_with(path_prefix.as_str()) {
+                    return false;
+                }
+            }
+            if let Some(extension) = &self.extension {
+                let file_name = url.path().rsplit('/').next().unwrap_or_default();
+                if !file_name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, url_extension)| {
+                        url_extension.eq_ignore_ascii_case(extension.trim_start_matches('.'))
+                    })
+                {
+                    return false;
+                }
+            }
+        }
+        if let Some(opener_name) = &self.opener_name {
+            if !context
+                .opener
+                .as_ref()
+                .is_some_and(|opener| glob_match(&opener_name.to_lowercase(), &opener.name))
+            {
+                return false;
+            }
+        }
+        if let Some(opener_path) = &self.opener_path {
+            if !context
+                .opener
+                .as_ref()
+                .and_then(|opener| opener.path.as_ref())
+                .is_some_and(|path| {
+                    glob_match(&opener_path.to_lowercase(), &path.to_string_lossy())
+                })
+            {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.regex {
+            let compiled = self.compiled_regex.0.get_or_init(|| {
+                Regex::new(regex)
+                    .inspect_err(|err| {
+                        warn!(
+                            "Invalid regex `{}` in a rule, treating it as not matching: {}",
+                            regex, err
+                        );
+                    })
+                    .ok()
+            });
+            if !compiled.as_ref().is_some_and(|regex| regex.is_match(uri)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Where a URI matched by a `Rule` gets opened
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RuleTarget {
+    /// The name of the installation, as shown in the dialog
+    pub installation: String,
+    /// `default` for the installation's default profile
+    pub profile: PathBuf,
+    /// The name of a container of the profile, for Firefox Multi-Account Containers
+    pub container: Option<String>,
+    /// Defaults to the preferred executable of the profile, otherwise the installation's
+    /// `exe_path`. Ignored for sandboxed installations.
+    pub executable: Option<PathBuf>,
+}
+
+impl RuleTarget {
+    /// Looks up the installation and profile of the target
+    ///
+    /// Returns `None` if either, or the container, can't be found in `installations`.
+    #[must_use]
+    pub fn resolve(&self, uri: &str, installations: &[Installation]) -> Option<CommandArguments> {
+        let installation = installations
+            .iter()
+            .find(|i| i.get_name() == self.installation)?;
+        let profile = installation.find_profile(&self.profile)?;
+        if let Some(container) = &self.container {
+            if !profile.containers().iter().any(|c| &c.name == container) {
+                warn!(
+                    "Profile {} has no container `{}`",
+                    profile.profile_path.display(),
+                    container
+                );
+                return None;
+            }
+        }
+        let executable = self
+            .executable
+            .as_ref()
+            .or_else(|| installation.preferred.get(&profile.profile_path))
+            .unwrap_or(&installation.exe_path);
+        Some(CommandArguments {
+            uris: vec![uri.to_string()],
+            browser_type: installation.browser_type.clone(),
+            executable: executable.clone(),
+            launch: installation.launch.clone(),
+            profile: profile.clone(),
+            container: self.container.clone(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Rule {
+    #[serde(flatten)]
+    pub matcher: Matcher,
+    #[serde(flatten)]
+    pub conditions: Conditions,
+    #[serde(flatten)]
+    pub target: RuleTarget,
+    /// Overrides whether tracking parameters are stripped from URIs routed by this rule
+    pub strip_tracking: Option<bool>,
+}
+
+/// The circumstances a URI is opened in, which rules can match on besides the URI itself
+#[derive(Debug, Clone)]
+pub struct RouteContext {
+    pub opener: Option<Opener>,
+    /// The local time conditions are checked against
+    pub now: NaiveDateTime,
+    pub network: Rc<dyn NetworkProbe>,
+    /// The environment variables `env` conditions are checked against
+    pub env: HashMap<String, String>,
+}
+
+impl RouteContext {
+    /// Gathers the context of the current process
+    #[must_use]
+    pub fn from_environment() -> Self {
+        Self {
+            opener: Opener::from_parent_process(),
+            now: Local::now().naive_local(),
+            network: Rc::new(SystemProbe::default()),
+            env: std::env::vars_os()
+                .map(|(name, value)| {
+                    (
+                        name.to_string_lossy().into_owned(),
+                        value.to_string_lossy().into_owned(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct RouteMatch {
+    /// Index of the matched rule in the rules it was routed with
+    pub rule_index: usize,
+    pub command_args: CommandArguments,
+}
+
+/// Finds the first rule matching the URI and resolves its target
+///
+/// Rules whose target can't be resolved are skipped with a warning. Returns `None` when no rule
+/// applies, in which case the dialog should be opened.
+#[must_use]
+pub fn route(
+    uri: &str,
+    rules: &[Rule],
+    installations: &[Installation],
+    context: &RouteContext,
+) -> Option<RouteMatch> {
+    let parsed = Url::parse(uri)
+        .inspect_err(|err| debug!("Could not parse URI `{}` as an URL: {}", uri, err))
+        .ok();
+    for (rule_index, rule) in rules.iter().enumerate() {
+        if !rule.matcher.matches(uri, parsed.as_ref(), context) || !rule.conditions.hold(context) {
+            continue;
+        }
+        let Some(command_args) = rule.target.resolve(uri, installations) else {
+            warn!(
+                "Rule {} matched, but its target `{}` with profile {:?} could not be found",
+                rule_index, rule.target.installation, rule.target.profile
+            );
+            continue;
+        };
+        info!("URI matched rule {}", rule_index);
+        return Some(RouteMatch {
+            rule_index,
+            command_args,
+        });
+    }
+    None
+}
+
+/// Case-insensitive glob supporting `*` and `?`, `pattern` is expected to be lowercase already
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, route, Matcher, RouteContext, Rule};
+    use crate::profile_loader::Installation;
+    use crate::router::network::SystemProbe;
+    use crate::router::opener::Opener;
+    use chrono::NaiveDateTime;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use url::Url;
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("*.example.com", "www.example.com"));
+        assert!(glob_match("*.example.com", "a.b.Example.COM"));
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxa"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn extension_of_the_last_path_segment() {
+        let context = RouteContext {
+            opener: None,
+            now: NaiveDateTime::default(),
+            network: Rc::new(SystemProbe::default()),
+            env: HashMap::new(),
+        };
+        let matcher = Matcher {
+            extension: Some(".pdf".to_string()),
+            ..Matcher::default()
+        };
+        let matches = |uri: &str| matcher.matches(uri, Url::parse(uri).ok().as_ref(), &context);
+        assert!(matches("file:///tmp/a.PDF"));
+        // The query isn't part of the path
+        assert!(matches("https://x/a.pdf?y"));
+        assert!(matches("https://x/dir.d/a.tar.pdf#page=2"));
+        assert!(!matches("https://x/a.pdf/"));
+        assert!(!matches("https://x/?file=a.pdf"));
+        assert!(!matches("https://x/pdf"));
+        assert!(!matches("not a uri.pdf"));
+    }
+
+    #[test]
+    fn regex_is_compiled_once() {
+        let context = RouteContext {
+            opener: None,
+            now: NaiveDateTime::default(),
+            network: Rc::new(SystemProbe::default()),
+            env: HashMap::new(),
+        };
+        let matcher = Matcher {
+            regex: Some("^https://[a-z]+\\.test/".to_string()),
+            ..Matcher::default()
+        };
+        assert!(matcher.matches("https://a.test/x", None, &context));
+        assert!(!matcher.matches("https://1.test/x", None, &context));
+        assert!(matcher.compiled_regex.0.get().is_some_and(Option::is_some));
+        let invalid = Matcher {
+            regex: Some("(".to_string()),
+            ..Matcher::default()
+        };
+        assert!(!invalid.matches("(", None, &context));
+        assert!(invalid.compiled_regex.0.get().is_some_and(Option::is_none));
+    }
+
+    #[test]
+    fn rules_for_the_opener() {
+        #[derive(Deserialize)]
+        struct Fixture {
+            rules: Vec<Rule>,
+            installations: Vec<Installation>,
+        }
+        let fixture: Fixture = toml::from_str(
+            r#"
+[[rules]]
+opener_name = "Thunder*"
+installation = "Firefox"
+profile = "/mail"
+
+[[rules]]
+opener_path = "/opt/*/slack"
+installation = "Firefox"
+profile = "/chat"
+
+[[installations]]
+name = "Firefox"
+browser_type = "Firefox"
+exe_path = "/bin/firefox"
+symlinks = []
+profiles = [
+    { name = "Mail", profile_path = "/mail" },
+    { name = "Chat", profile_path = "/chat" },
+]
+
+[installations.preferred]
+"#,
+        )
+        .unwrap();
+        let rule_index = |opener: Option<Opener>| {
+            let context = RouteContext {
+                opener,
+                now: NaiveDateTime::default(),
+                network: Rc::new(SystemProbe::default()),
+                env: HashMap::new(),
+            };
+            route(
+                "https://example.com/",
+                &fixture.rules,
+                &fixture.installations,
+                &context,
+            )
+            .map(|route_match| route_match.rule_index)
+        };
+        assert_eq!(
+            rule_index(Some(Opener::from_path(PathBuf::from(
+                "/usr/lib/thunderbird/thunderbird"
+            )))),
+            Some(0)
+        );
+        assert_eq!(
+            rule_index(Some(Opener::from_path(PathBuf::from("/opt/Slack/slack")))),
+            Some(1)
+        );
+        assert_eq!(
+            rule_index(Some(Opener::from_path(PathBuf::from("/opt/slack")))),
+            None
+        );
+        // Only the name could be read for processes of other users
+        let without_path = Opener {
+            name: "slack".to_string(),
+            path: None,
+        };
+        assert_eq!(rule_index(Some(without_path)), None);
+        let name_only = Opener {
+            name: "thunderbird".to_string(),
+            path: None,
+        };
+        assert_eq!(rule_index(Some(name_only)), Some(0));
+        assert_eq!(rule_index(None), None);
+    }
+}
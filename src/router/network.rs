@@ -0,0 +1,248 @@
+use log::debug;
+use std::fmt::Debug;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+
+/// Reads the local network state network conditions are checked against
+///
+/// Implementations only report the raw state, matching it is up to the conditions.
+pub trait NetworkProbe: Debug {
+    /// Names of the interfaces which are up
+    fn interfaces_up(&self) -> Vec<String>;
+    /// Destinations of every route with their prefix lengths, including the default routes
+    fn routes(&self) -> Vec<(IpAddr, u8)>;
+    /// DNS search domains, lowercase and without a trailing `.`
+    fn search_domains(&self) -> Vec<String>;
+}
+
+/// Reads the network state from `/sys`, `/proc` and `/etc/resolv.conf`, below `root`
+///
+/// Only finds something on Linux. Pointing `root` at a directory mirroring those paths allows
+/// checking rules against a captured network state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemProbe {
+    pub root: PathBuf,
+}
+
+impl Default for SystemProbe {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+        }
+    }
+}
+
+impl SystemProbe {
+    #[must_use]
+    pub fn with_root(root: PathBuf) -> Self {
+        Self { root }
+    }
+    fn read(&self, path: &str) -> Option<String> {
+        let path = self.root.join(path);
+        std::fs::read_to_string(&path)
+            .inspect_err(|err| debug!("Failed to read {:?}: {}", path, err))
+            .ok()
+    }
+}
+
+impl NetworkProbe for SystemProbe {
+    fn interfaces_up(&self) -> Vec<String> {
+        let net_dir = self.root.join("sys/class/net");
+        let Ok(entries) = std::fs::read_dir(&net_dir)
+            .inspect_err(|err| debug!("Failed to list {:?}: {}", net_dir, err))
+        else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| interface_is_up(&entry.path()))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+    fn routes(&self) -> Vec<(IpAddr, u8)> {
+        let mut routes = Vec::new();
+        // Iface Destination Gateway Flags RefCnt Use Metric Mask..., addresses in host order hex
+        if let Some(table) = self.read("proc/net/route") {
+            routes.extend(table.lines().skip(1).filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let destination = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+                let mask = u32::from_str_radix(fields.get(7)?, 16).ok()?;
+                #[expect(clippy::cast_possible_truncation)] // At most 32 ones
+                Some((
+                    IpAddr::V4(Ipv4Addr::from(destination.to_ne_bytes())),
+                    mask.count_ones() as u8,
+                ))
+            }));
+        }
+        // Destination PrefixLength Source..., addresses in network order hex
+        if let Some(table) = self.read("proc/net/ipv6_route") {
+            routes.extend(table.lines().filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let destination = u128::from_str_radix(fields.first()?, 16).ok()?;
+                let prefix = u8::from_str_radix(fields.get(1)?, 16).ok()?;
+                Some((IpAddr::V6(Ipv6Addr::from(destination)), prefix))
+            }));
+        }
+        routes
+    }
+    fn search_domains(&self) -> Vec<String> {
+        let Some(resolv_conf) = self.read("etc/resolv.conf") else {
+            return Vec::new();
+        };
+        resolv_conf
+            .lines()
+            .map(str::split_whitespace)
+            .filter_map(|mut fields| {
+                matches!(fields.next(), Some("search" | "domain")).then_some(fields)
+            })
+            .flatten()
+            .map(|domain| domain.trim_end_matches('.').to_lowercase())
+            .collect()
+    }
+}
+
+/// VPN tunnels usually report their operational state as `unknown`, so the administrative
+/// `IFF_UP` flag decides for those
+fn interface_is_up(interface_dir: &Path) -> bool {
+    let read = |file: &str| std::fs::read_to_string(interface_dir.join(file)).ok();
+    match read("operstate").as_deref().map(str::trim) {
+        Some("up") => true,
+        Some("unknown") => read("flags")
+            .and_then(|flags| u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16).ok())
+            .is_some_and(|flags| flags & 0x1 != 0),
+        _ => false,
+    }
+}
+
+/// Parses `address/prefix`, a plain address is a network with the full prefix length
+///
+/// # Errors
+///
+/// Returns a description of why `network` is invalid
+pub fn parse_network(network: &str) -> Result<(IpAddr, u8), String> {
+    let (address, prefix) = network.split_once('/').unwrap_or((network, ""));
+    let address: IpAddr = address
+        .trim()
+        .parse()
+        .map_err(|err| format!("Invalid address in network `{network}`: {err}"))?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = if prefix.is_empty() {
+        max_prefix
+    } else {
+        prefix
+            .trim()
+            .parse()
+            .ok()
+            .filter(|prefix| *prefix <= max_prefix)
+            .ok_or_else(|| format!("Invalid prefix length in network `{network}`"))?
+    };
+    Ok((address, prefix))
+}
+
+/// Whether the route sends the whole network through it
+///
+/// Default routes are ignored, they would contain every network.
+#[must_use]
+pub fn route_contains(route: (IpAddr, u8), network: (IpAddr, u8)) -> bool {
+    let (route_prefix, network_prefix) = (route.1, network.1);
+    if route_prefix == 0 || route_prefix > network_prefix {
+        return false;
+    }
+    match (route.0, network.0) {
+        (IpAddr::V4(route_address), IpAddr::V4(network_address)) => {
+            let mask = u32::MAX << (32 - u32::from(route_prefix));
+            u32::from(route_address) & mask == u32::from(network_address) & mask
+        }
+        (IpAddr::V6(route_address), IpAddr::V6(network_address)) => {
+            let mask = u128::MAX << (128 - u32::from(route_prefix));
+            u128::from(route_address) & mask == u128::from(network_address) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_network, route_contains, NetworkProbe, SystemProbe};
+    use std::fs;
+    use std::net::IpAddr;
+
+    fn network(network: &str) -> (IpAddr, u8) {
+        parse_network(network).unwrap()
+    }
+
+    #[test]
+    fn networks() {
+        assert_eq!(network("10.20.0.0/16"), ("10.20.0.0".parse().unwrap(), 16));
+        assert_eq!(network(" 10.20.1.2 "), ("10.20.1.2".parse().unwrap(), 32));
+        assert_eq!(network("fd00::/8"), ("fd00::".parse().unwrap(), 8));
+        assert_eq!(network("fd00::1").1, 128);
+        assert!(parse_network("10.20.0.0/33").is_err());
+        assert!(parse_network("10.20.0/16").is_err());
+        assert!(parse_network("10.20.0.0/x").is_err());
+    }
+
+    #[test]
+    fn routes_contain_networks() {
+        let route = network("10.20.0.0/16");
+        assert!(route_contains(route, network("10.20.0.0/16")));
+        assert!(route_contains(route, network("10.20.30.0/24")));
+        assert!(route_contains(route, network("10.20.255.1")));
+        assert!(!route_contains(route, network("10.0.0.0/8")));
+        assert!(!route_contains(route, network("10.21.0.1")));
+        assert!(!route_contains(network("0.0.0.0/0"), network("10.20.0.1")));
+        assert!(route_contains(network("fd00::/8"), network("fd12::1")));
+        assert!(!route_contains(network("fd00::/8"), network("10.20.0.1")));
+    }
+
+    #[test]
+    fn reads_a_captured_network_state() {
+        let root = std::env::temp_dir().join(format!("plinks-probe-{}", std::process::id()));
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("sys/class/net/eth0/operstate", "up\n");
+        write("sys/class/net/wlan0/operstate", "down\n");
+        write("sys/class/net/tun0/operstate", "unknown\n");
+        write("sys/class/net/tun0/flags", "0x1091\n");
+        write("sys/class/net/tun1/operstate", "unknown\n");
+        write("sys/class/net/tun1/flags", "0x1090\n");
+        write(
+            "proc/net/route",
+            "Iface\tDestination\tGateway\tFlags\tRefCnt\tUse\tMetric\tMask\tMTU\tWindow\tIRTT\n\
+             eth0\t00000000\t0100A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+             tun0\t0000140A\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0\n",
+        );
+        write(
+            "proc/net/ipv6_route",
+            "fd000000000000000000000000000000 08 00000000000000000000000000000000 00 \
+             00000000000000000000000000000000 00000400 00000001 00000000 00000001 tun0\n",
+        );
+        write(
+            "etc/resolv.conf",
+            "# Generated\nnameserver 10.20.0.1\nsearch Corp.Example.com. other.net\n",
+        );
+        let probe = SystemProbe::with_root(root.clone());
+        let mut interfaces = probe.interfaces_up();
+        interfaces.sort();
+        assert_eq!(interfaces, vec!["eth0", "tun0"]);
+        assert_eq!(
+            probe.routes(),
+            vec![
+                network("0.0.0.0/0"),
+                network("10.20.0.0/16"),
+                network("fd00::/8")
+            ]
+        );
+        assert_eq!(
+            probe.search_domains(),
+            vec!["corp.example.com", "other.net"]
+        );
+        fs::remove_dir_all(root).unwrap();
+        assert!(SystemProbe::with_root("/nonexistent".into())
+            .routes()
+            .is_empty());
+    }
+}
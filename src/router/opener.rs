@@ -0,0 +1,58 @@
+use log::debug;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The application a URI was opened from
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Opener {
+    /// The executable's file name, e.g. `thunderbird`
+    pub name: String,
+    pub path: Option<PathBuf>,
+}
+
+impl Opener {
+    #[must_use]
+    pub fn from_path(path: PathBuf) -> Self {
+        Self {
+            name: path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            path: Some(path),
+        }
+    }
+    /// The parent of the current process, which usually is the application the link was
+    /// clicked in
+    ///
+    /// Only implemented on Linux, where it is read from `/proc`. If the parent is a launcher
+    /// script like `xdg-open`, that script will be the opener.
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn from_parent_process() -> Option<Self> {
+        Self::from_proc(std::os::unix::process::parent_id())
+    }
+    #[cfg(not(target_os = "linux"))]
+    #[must_use]
+    pub fn from_parent_process() -> Option<Self> {
+        None
+    }
+    #[cfg(target_os = "linux")]
+    fn from_proc(pid: u32) -> Option<Self> {
+        let proc_dir = PathBuf::from(format!("/proc/{pid}"));
+        match std::fs::read_link(proc_dir.join("exe")) {
+            Ok(path) => Some(Self::from_path(path)),
+            Err(err) => {
+                // Processes of other users can't have their `exe` read, `comm` is still readable
+                debug!("Failed to read the executable of process {}: {}", pid, err);
+                let comm = std::fs::read_to_string(proc_dir.join("comm"))
+                    .inspect_err(|err| {
+                        debug!("Failed to read the name of process {}: {}", pid, err)
+                    })
+                    .ok()?;
+                Some(Self {
+                    name: comm.trim_end().to_string(),
+                    path: None,
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use crate::profile_loader::Installation;
+use crate::router::{RouteContext, RuleTarget};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A user script deciding where URIs go, before the rules are tried
+///
+/// The script has to define `fn route(request)`. `request` is a map with the `uri`, its
+/// `scheme`, `host` and `path`, the `opener` as `#{ name, path }` and the `installations` as
+/// `[#{ name, browser_type, profiles: [#{ name, path }] }]`. It returns one of:
+/// - `()` to leave the decision to the rules
+/// - `"ask"` to open the dialog
+/// - `#{ installation, profile }`, optionally with an `executable` and `container`, to open the
+///   URI there
+/// - `#{ uri }` to rewrite the URI, which can be combined with the target or `ask: true`
+///
+/// Scripts run sandboxed, without access to files, modules or `eval`. Only available with the
+/// `scripting` feature, otherwise configured scripts are ignored with a warning.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScriptConfig {
+    /// Path to a Rhai script
+    pub path: Option<PathBuf>,
+    /// After this many milliseconds the script is aborted and the rules decide
+    pub timeout_ms: u64,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            timeout_ms: 250,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub enum ScriptAction {
+    /// The rules decide, this also covers scripts which failed or timed out
+    #[default]
+    Continue,
+    Ask,
+    Route(RuleTarget),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct ScriptDecision {
+    /// The URI rewritten by the script
+    pub uri: Option<String>,
+    pub action: ScriptAction,
+}
+
+impl ScriptConfig {
+    /// Runs the script for the URI, if one is configured
+    #[cfg(feature = "scripting")]
+    #[must_use]
+    pub fn decide(
+        &self,
+        uri: &str,
+        installations: &[Installation],
+        context: &RouteContext,
+    ) -> ScriptDecision {
+        let Some(path) = &self.path else {
+            return ScriptDecision::default();
+        };
+        match sandbox::run(path, self.timeout_ms, uri, installations, context) {
+            Ok(decision) => {
+                log::info!("Script decided {:?}", decision);
+                decision
+            }
+            Err(err) => {
+                log::warn!("Script {:?} failed, leaving it to the rules: {}", path, err);
+                ScriptDecision::default()
+            }
+        }
+    }
+    #[cfg(not(feature = "scripting"))]
+    #[must_use]
+    pub fn decide(
+        &self,
+        _uri: &str,
+        _installations: &[Installation],
+        _context: &RouteContext,
+    ) -> ScriptDecision {
+        if let Some(path) = &self.path {
+            log::warn!(
+                "Script {:?} is configured, but plinks was built without the `scripting` feature",
+                path
+            );
+        }
+        ScriptDecision::default()
+    }
+}
+
+#[cfg(feature = "scripting")]
+mod sandbox {
+    use super::{ScriptAction, ScriptDecision};
+    use crate::profile_loader::Installation;
+    use crate::router::{RouteContext, RuleTarget};
+    use rhai::module_resolvers::DummyModuleResolver;
+    use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+    use std::cell::{Cell, RefCell};
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    /// A script read and compiled once, to be run for every URI
+    struct Compiled {
+        engine: Engine,
+        ast: AST,
+        /// When the current run started, for the timeout
+        started: Rc<Cell<Instant>>,
+    }
+
+    type CompileResult = Result<Rc<Compiled>, String>;
+
+    thread_local! {
+        /// Scripts stay compiled for the lifetime of the process, failures are remembered as well
+        static COMPILED: RefCell<Vec<(PathBuf, CompileResult)>> =
+            const { RefCell::new(Vec::new()) };
+    }
+
+    pub fn run(
+        path: &Path,
+        timeout_ms: u64,
+        uri: &str,
+        installations: &[Installation],
+        context: &RouteContext,
+    ) -> Result<ScriptDecision, String> {
+        let compiled = compile_cached(path, timeout_ms)?;
+        compiled.started.set(Instant::now());
+        let result: Dynamic = compiled
+            .engine
+            .call_fn(
+                &mut Scope::new(),
+                &compiled.ast,
+                "route",
+                (request(uri, installations, context),),
+            )
+            .map_err(|err| err.to_string())?;
+        decision(result)
+    }
+
+    /// Compiles the script at `path`, or returns it if it was compiled before
+    fn compile_cached(path: &Path, timeout_ms: u64) -> CompileResult {
+        COMPILED.with_borrow_mut(|compiled| {
+            if let Some((_, script)) = compiled
+                .iter()
+                .find(|(compiled_path, _)| compiled_path == path)
+            {
+                return script.clone();
+            }
+            let script = compile(path, timeout_ms).map(Rc::new);
+            compiled.push((path.to_path_buf(), script.clone()));
+            script
+        })
+    }
+
+    fn compile(path: &Path, timeout_ms: u64) -> Result<Compiled, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let started = Rc::new(Cell::new(Instant::now()));
+        let engine = engine(Duration::from_millis(timeout_ms), Rc::clone(&started));
+        let ast = engine.compile(&source).map_err(|err| err.to_string())?;
+        log::info!("Compiled script {:?}", path);
+        Ok(Compiled {
+            engine,
+            ast,
+            started,
+        })
+    }
+
+    fn engine(timeout: Duration, started: Rc<Cell<Instant>>) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(64 * 1024);
+        engine.set_max_array_size(16 * 1024);
+        engine.set_max_map_size(16 * 1024);
+        engine.on_print(|text| log::info!("Script: {}", text));
+        engine.on_debug(|text, _, _| log::debug!("Script: {}", text));
+        engine.on_progress(move |_| (started.get().elapsed() > timeout).then_some(Dynamic::UNIT));
+        engine
+    }
+
+    fn request(uri: &str, installations: &[Installation], context: &RouteContext) -> Map {
+        let parsed = url::Url::parse(uri).ok();
+        let mut request = Map::new();
+        request.insert("uri".into(), uri.into());
+        request.insert(
+            "scheme".into(),
+            parsed
+                .as_ref()
+                .map(url::Url::scheme)
+                .unwrap_or_default()
+                .into(),
+        );
+        request.insert(
+            "host".into(),
+            parsed
+                .as_ref()
+                .and_then(url::Url::host_str)
+                .unwrap_or_default()
+                .into(),
+        );
+        request.insert(
+            "path".into(),
+            parsed
+                .as_ref()
+                .map(url::Url::path)
+                .unwrap_or_default()
+                .into(),
+        );
+        let mut opener = Map::new();
+        if let Some(context_opener) = &context.opener {
+            opener.insert("name".into(), context_opener.name.clone().into());
+            opener.insert(
+                "path".into(),
+                context_opener
+                    .path
+                    .as_ref()
+                    .map_or(Dynamic::UNIT, |path| path_value(path)),
+            );
+        }
+        request.insert("opener".into(), opener.into());
+        let installations: Array = installations
+            .iter()
+            .map(|installation| {
+                let mut map = Map::new();
+                map.insert("name".into(), installation.get_name().into());
+                map.insert(
+                    "browser_type".into(),
+                    installation.browser_type.get_name().into(),
+                );
+                let profiles: Array = installation
+                    .profiles
+                    .iter()
+                    .map(|profile| {
+                        let mut map = Map::new();
+                        map.insert("name".into(), profile.to_string().into());
+                        map.insert("path".into(), path_value(&profile.profile_path));
+                        map.into()
+                    })
+                    .collect();
+                map.insert("profiles".into(), profiles.into());
+                map.into()
+            })
+            .collect();
+        request.insert("installations".into(), installations.into());
+        request
+    }
+
+    fn path_value(path: &Path) -> Dynamic {
+        path.to_string_lossy().into_owned().into()
+    }
+
+    fn decision(result: Dynamic) -> Result<ScriptDecision, String> {
+        if result.is_unit() {
+            return Ok(ScriptDecision::default());
+        }
+        if result.is_string() {
+            return match result.into_string()?.as_str() {
+                "ask" => Ok(ScriptDecision {
+                    uri: None,
+                    action: ScriptAction::Ask,
+                }),
+                other => Err(format!("Returned the unknown decision `{other}`")),
+            };
+        }
+        let Some(map) = result.try_cast::<Map>() else {
+            return Err("Returned neither `()`, `\"ask\"` nor a map".to_string());
+        };
+        let string = |key: &str| -> Result<Option<String>, String> {
+            map.get(key)
+                .filter(|value| !value.is_unit())
+                .map(|value| {
+                    value
+                        .clone()
+                        .into_string()
+                        .map_err(|type_name| format!("`{key}` is a {type_name}, not a string"))
+                })
+                .transpose()
+        };
+        let uri = string("uri")?;
+        let ask = map
+            .get("ask")
+            .is_some_and(|ask| ask.as_bool().unwrap_or(false));
+        let action = match (string("installation")?, string("profile")?) {
+            _ if ask => ScriptAction::Ask,
+            (Some(installation), Some(profile)) => ScriptAction::Route(RuleTarget {
+                installation,
+                profile: PathBuf::from(profile),
+                executable: string("executable")?.map(PathBuf::from),
+                container: string("container")?,
+            }),
+            (None, None) => ScriptAction::Continue,
+            _ => return Err("Returned only one of `installation` and `profile`".to_string()),
+        };
+        Ok(ScriptDecision { uri, action })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{decision, run, COMPILED};
+        use crate::router::network::SystemProbe;
+        use crate::router::script::{ScriptAction, ScriptDecision};
+        use crate::router::RouteContext;
+        use chrono::NaiveDateTime;
+        use rhai::{Dynamic, Engine};
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        #[test]
+        fn compiled_once_and_timed_out_per_run() {
+            let path = std::env::temp_dir().join(format!("plinks-{}.rhai", std::process::id()));
+            std::fs::write(
+                &path,
+                r#"
+fn route(request) {
+    if request.host == "loop.test" { loop {} }
+    if request.host.ends_with(".corp.com") {
+        return #{ installation: "Firefox", profile: "/p", uri: request.uri + "?via=script" };
+    }
+    "ask"
+}
+"#,
+            )
+            .unwrap();
+            let context = RouteContext {
+                opener: None,
+                now: NaiveDateTime::default(),
+                network: Rc::new(SystemProbe::default()),
+                env: HashMap::new(),
+            };
+            let decision = run(&path, 50, "https://a.corp.com/", &[], &context).unwrap();
+            assert_eq!(
+                decision.uri.as_deref(),
+                Some("https://a.corp.com/?via=script")
+            );
+            assert!(
+                matches!(decision.action, ScriptAction::Route(target) if target.installation == "Firefox")
+            );
+            // Changes aren't picked up anymore, the script was compiled already
+            std::fs::write(&path, "fn route(request) { }").unwrap();
+            let decision = run(&path, 50, "https://example.com/", &[], &context).unwrap();
+            assert_eq!(decision.action, ScriptAction::Ask);
+            assert!(run(&path, 50, "https://loop.test/", &[], &context).is_err());
+            assert_eq!(
+                run(&path, 50, "https://example.com/", &[], &context)
+                    .unwrap()
+                    .action,
+                ScriptAction::Ask
+            );
+            assert_eq!(
+                COMPILED.with_borrow(|compiled| compiled
+                    .iter()
+                    .filter(|(compiled_path, _)| *compiled_path == path)
+                    .count()),
+                1
+            );
+            std::fs::remove_file(path).unwrap();
+        }
+
+        fn decide(source: &str) -> Result<ScriptDecision, String> {
+            decision(Engine::new().eval::<Dynamic>(source).unwrap())
+        }
+
+        #[test]
+        fn decisions() {
+            assert_eq!(decide("()"), Ok(ScriptDecision::default()));
+            assert_eq!(decide(r#""ask""#).unwrap().action, ScriptAction::Ask);
+            let routed = decide(
+                r#"#{ installation: "Firefox", profile: "/p", container: "Work", uri: "https://a.b/" }"#,
+            )
+            .unwrap();
+            assert_eq!(routed.uri.as_deref(), Some("https://a.b/"));
+            assert!(matches!(
+                routed.action,
+                ScriptAction::Route(target)
+                    if target.container.as_deref() == Some("Work") && target.executable.is_none()
+            ));
+            assert_eq!(
+                decide(r#"#{ uri: "https://a.b/" }"#).unwrap(),
+                ScriptDecision {
+                    uri: Some("https://a.b/".to_string()),
+                    action: ScriptAction::Continue
+                }
+            );
+        }
+
+        #[test]
+        fn ask_wins_over_a_target() {
+            assert_eq!(
+                decide(r#"#{ installation: "Firefox", profile: "/p", ask: true }"#)
+                    .unwrap()
+                    .action,
+                ScriptAction::Ask
+            );
+        }
+
+        #[test]
+        fn invalid_decisions() {
+            assert!(decide(r#"#{ installation: "Firefox" }"#).is_err());
+            assert!(decide(r#"#{ profile: "/p" }"#).is_err());
+            assert!(decide(r#""open""#).is_err());
+            assert!(decide("42").is_err());
+            assert!(decide("#{ uri: 42 }").is_err());
+            assert!(decide(r#"#{ installation: "Firefox", profile: ["/p"] }"#).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScriptConfig, ScriptDecision};
+    use crate::router::network::SystemProbe;
+    use crate::router::RouteContext;
+    use chrono::NaiveDateTime;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    #[test]
+    fn failing_scripts_leave_it_to_the_rules() {
+        let context = RouteContext {
+            opener: None,
+            now: NaiveDateTime::default(),
+            network: Rc::new(SystemProbe::default()),
+            env: HashMap::new(),
+        };
+        for path in [None, Some("/nonexistent/route.rhai".into())] {
+            let config = ScriptConfig {
+                path,
+                ..ScriptConfig::default()
+            };
+            assert_eq!(
+                config.decide("https://a.b/", &[], &context),
+                ScriptDecision::default()
+            );
+        }
+    }
+}
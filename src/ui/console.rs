@@ -1,18 +1,20 @@
 use crate::app::CommandArguments;
-use crate::profile_loader::{Installation, Profile};
+use crate::profile_loader::{
+    Container, Installation, LaunchStyle, Profile, ProfileMetadata, ProfileOrder,
+};
+use crate::ui::DialogConfig;
 use inquire::error::InquireResult;
-use inquire::formatter::OptionFormatter;
 use inquire::list_option::ListOption;
 use inquire::{Confirm, InquireError, Select};
-use log::{debug, error, info, trace, warn};
-use std::ffi::{OsStr, OsString};
+use log::{error, info};
 use std::fmt::{Display, Formatter};
-use std::ops::Index;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 enum Choice<T: Display> {
     LastUsed,
     Option(T),
+    /// Going on without choosing any of the options
+    Skip,
     Back,
 }
 impl<T: Display> Display for Choice<T> {
@@ -24,6 +26,9 @@ impl<T: Display> Display for Choice<T> {
             Choice::Option(object) => {
                 write!(f, "{object}")
             }
+            Choice::Skip => {
+                write!(f, "None")
+            }
             Choice::Back => {
                 write!(f, "Back")
             }
@@ -38,11 +43,38 @@ impl<T: std::fmt::Debug> Display for DebugDisplay<T> {
     }
 }
 
+/// A profile as listed in the dialog
+struct ProfileEntry<'a> {
+    profile: &'a Profile,
+    /// What the browser would pick by itself
+    is_default: bool,
+    /// Only set if it is shown
+    metadata: Option<ProfileMetadata>,
+}
+impl Display for ProfileEntry<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.profile)?;
+        if self.is_default {
+            write!(f, " (default)")?;
+        }
+        match &self.metadata {
+            Some(metadata) if *metadata != ProfileMetadata::default() => {
+                write!(f, " - {metadata}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 pub struct CommandArgsBuilder<'a> {
     pub installations: &'a mut Vec<Installation>,
-    pub uri: String,
+    pub dialog: &'a DialogConfig,
+    pub uris: Vec<String>,
+    /// Shown above the URIs, e.g. about lookalike domains
+    pub warnings: Vec<String>,
     pub selected_installation_idx: Option<usize>,
     pub selected_profile: Option<Profile>,
+    pub selected_container: Option<String>,
     pub selected_exe_path: Option<PathBuf>,
 }
 impl CommandArgsBuilder<'_> {
@@ -55,9 +87,11 @@ impl CommandArgsBuilder<'_> {
         }
         Some(CommandArguments {
             browser_type: self.selected_installation().unwrap().browser_type.clone(),
-            uri: self.uri,
+            launch: self.selected_installation().unwrap().launch.clone(),
+            uris: self.uris,
             executable: self.selected_exe_path.unwrap(),
             profile: self.selected_profile.unwrap(),
+            container: self.selected_container,
         })
     }
     pub fn selected_installation(&mut self) -> Option<&mut Installation> {
@@ -86,12 +120,21 @@ fn unpack_inquireresult<T>(res: InquireResult<T>) -> Option<T> {
 }
 
 fn prompt_for_installation(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArguments> {
+    let mut message: String = builder
+        .warnings
+        .iter()
+        .map(|warning| format!("{warning}\n"))
+        .collect();
+    if let [uri] = builder.uris.as_slice() {
+        message += &format!("URI: {uri}\nWhich installation would you like to open it with?");
+    } else {
+        message += &format!(
+            "URIs:\n  {}\nWhich installation would you like to open them with?",
+            builder.uris.join("\n  ")
+        );
+    }
     let ans: InquireResult<ListOption<String>> = Select::new(
-        format!(
-            "URI: {}\nWhich installation would you like to open it with?",
-            builder.uri
-        )
-        .as_str(),
+        message.as_str(),
         builder
             .installations
             .iter()
@@ -105,14 +148,40 @@ fn prompt_for_installation(mut builder: Box<CommandArgsBuilder>) -> Option<Comma
 }
 
 fn prompt_for_profile(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArguments> {
-    let ans: InquireResult<Choice<&Profile>> = Select::new(
+    let dialog = builder.dialog;
+    let installation = builder
+        .selected_installation()
+        .expect("Prompting for profile without a selected installation!");
+    // Reading the metadata can take a while, so only if it is needed
+    let read_metadata = dialog.show_metadata || dialog.profile_order != ProfileOrder::Listed;
+    let with_size = dialog.show_size || dialog.profile_order == ProfileOrder::Size;
+    let mut profiles: Vec<(&Profile, ProfileMetadata)> = installation
+        .profiles
+        .iter()
+        .map(|p| {
+            let metadata = if read_metadata {
+                p.metadata(with_size)
+            } else {
+                ProfileMetadata::default()
+            };
+            (p, metadata)
+        })
+        .collect();
+    dialog.profile_order.sort(&mut profiles);
+    // The default profile comes first, so it is preselected
+    profiles.sort_by_key(|(p, _)| installation.default_profile.as_ref() != Some(&p.profile_path));
+    let ans: InquireResult<Choice<ProfileEntry>> = Select::new(
         "Which profile would you like to use?",
-        builder
-            .selected_installation()
-            .expect("Prompting for profile without a selected installation!")
-            .profiles
-            .iter()
-            .map(Choice::Option)
+        profiles
+            .into_iter()
+            .map(|(profile, metadata)| {
+                Choice::Option(ProfileEntry {
+                    profile,
+                    is_default: installation.default_profile.as_ref()
+                        == Some(&profile.profile_path),
+                    metadata: dialog.show_metadata.then_some(metadata),
+                })
+            })
             .chain(std::iter::once(Choice::Back))
             .collect(),
     )
@@ -120,8 +189,38 @@ fn prompt_for_profile(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArg
     let ans = unpack_inquireresult(ans)?;
     match ans {
         Choice::Back => prompt_for_installation(builder),
-        Choice::Option(profile) => {
+        Choice::Option(ProfileEntry { profile, .. }) => {
             builder.selected_profile = Some(profile.clone());
+            builder.selected_container = None;
+            prompt_for_container(builder)
+        }
+        Choice::LastUsed | Choice::Skip => unreachable!(),
+    }
+}
+
+fn prompt_for_container(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArguments> {
+    let containers = builder
+        .selected_profile
+        .as_ref()
+        .expect("Prompting for container without a selected profile!")
+        .containers();
+    if containers.is_empty() {
+        return prompt_for_exe_path(builder);
+    }
+    let ans: InquireResult<Choice<&Container>> = Select::new(
+        "Which container would you like to use?",
+        std::iter::once(Choice::Skip)
+            .chain(containers.iter().map(Choice::Option))
+            .chain(std::iter::once(Choice::Back))
+            .collect(),
+    )
+    .prompt();
+    let ans = unpack_inquireresult(ans)?;
+    match ans {
+        Choice::Back => prompt_for_profile(builder),
+        Choice::Skip => prompt_for_exe_path(builder),
+        Choice::Option(container) => {
+            builder.selected_container = Some(container.name.clone());
             prompt_for_exe_path(builder)
         }
         Choice::LastUsed => unreachable!(),
@@ -129,6 +228,16 @@ fn prompt_for_profile(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArg
 }
 
 fn prompt_for_exe_path(mut builder: Box<CommandArgsBuilder>) -> Option<CommandArguments> {
+    let installation = builder.selected_installation().unwrap();
+    // Sandboxes are started the same way no matter the executable
+    if installation.launch != LaunchStyle::Executable {
+        builder.selected_exe_path = Some(installation.exe_path.clone());
+        return Some(
+            builder
+                .into_commandargs()
+                .expect("Invalid state in CommandArgsBuilder encountered"),
+        );
+    }
     let installation = builder
         .installations
         .get_mut(
@@ -180,6 +289,7 @@ fn prompt_for_exe_path(mut builder: Box<CommandArgsBuilder>) -> Option<CommandAr
                     .expect("Invalid state in CommandArgsBuilder encountered"),
             )
         }
+        Choice::Skip => unreachable!(),
         Choice::Option(val) => {
             builder.selected_exe_path = Some(val.0.clone());
             installation
@@ -194,14 +304,31 @@ fn prompt_for_exe_path(mut builder: Box<CommandArgsBuilder>) -> Option<CommandAr
     }
 }
 
-pub fn open_dialog(uri: String, installations: &mut Vec<Installation>) -> Option<CommandArguments> {
+pub fn open_dialog(
+    uris: Vec<String>,
+    warnings: Vec<String>,
+    installations: &mut Vec<Installation>,
+    dialog: &DialogConfig,
+) -> Option<CommandArguments> {
     // Need to Box to use explicit tail calls (PassMode::Indirect unsupported)
     // Using become seems to break inquire
     prompt_for_installation(Box::from(CommandArgsBuilder {
-        uri,
+        uris,
+        warnings,
         installations,
+        dialog,
         selected_installation_idx: None,
         selected_profile: None,
+        selected_container: None,
         selected_exe_path: None,
     }))
 }
+
+pub fn confirm_uri(uri: &str, scheme: &str) -> bool {
+    let ans = Confirm::new(&format!(
+        "URI: {uri}\nIt uses the scheme `{scheme}`, would you like to open it anyway?"
+    ))
+    .with_default(false)
+    .prompt();
+    unpack_inquireresult(ans).unwrap_or(false)
+}
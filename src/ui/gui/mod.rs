@@ -1,11 +1,14 @@
-use crate::app::CommandArguments;
-use crate::profile_loader::Installation;
-use qmetaobject::prelude::*;
-
-pub fn open_dialog(
-    uri: String,
-    installations: Vec<&mut Installation>,
-) -> Option<CommandArguments> {
-    qmetaobject::log::init_qt_to_rust();
-    todo!()
-}
+use crate::app::CommandArguments;
+use crate::profile_loader::Installation;
+use crate::ui::DialogConfig;
+use qmetaobject::prelude::*;
+
+pub fn open_dialog(
+    uris: Vec<String>,
+    warnings: Vec<String>,
+    installations: Vec<&mut Installation>,
+    config: &DialogConfig,
+) -> Option<CommandArguments> {
+    qmetaobject::log::init_qt_to_rust();
+    todo!()
+}
@@ -3,36 +3,92 @@ mod console;
 #[cfg(feature = "GUI")]
 mod gui;
 
-use crate::profile_loader::Installation;
-use std::process::Command;
+use crate::app::CommandArguments;
+use crate::profile_loader::{Installation, ProfileOrder};
+use serde::{Deserialize, Serialize};
 
+/// How the dialog lists profiles, the default profile always comes first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct DialogConfig {
+    pub profile_order: ProfileOrder,
+    /// Show when profiles were created and last used, which reads their directories
+    pub show_metadata: bool,
+    /// Also show the size of profiles, which walks over every file in them
+    pub show_size: bool,
+}
+
+#[cfg(all(feature = "CLI", not(feature = "GUI")))]
+#[must_use]
+pub fn open_dialog(
+    uris: Vec<String>,
+    warnings: Vec<String>,
+    installations: &mut Vec<Installation>,
+    config: &DialogConfig,
+) -> Option<CommandArguments> {
+    console::open_dialog(uris, warnings, installations, config)
+}
+
+#[cfg(all(feature = "GUI", not(feature = "CLI")))]
+#[must_use]
+pub fn open_dialog(
+    uris: Vec<String>,
+    warnings: Vec<String>,
+    installations: &mut Vec<Installation>,
+    config: &DialogConfig,
+) -> Option<CommandArguments> {
+    gui::open_dialog(uris, warnings, installations, config)
+}
+
+#[cfg(any(
+    all(feature = "CLI", feature = "GUI"),
+    all(not(feature = "CLI"), not(feature = "GUI"))
+))]
+pub fn open_dialog(
+    uris: Vec<String>,
+    warnings: Vec<String>,
+    installations: &mut Vec<Installation>,
+    config: &DialogConfig,
+) -> CommandArguments {
+    compile_error!("You need to select either GUI or CLI!")
+}
+
+/// Asks whether the URI of a scheme, which isn't allowed outright, should be opened
+///
+/// Returns `false` if it can't be asked, e.g. without a terminal for the console dialog.
 #[cfg(all(feature = "CLI", not(feature = "GUI")))]
 #[must_use]
-pub fn open_dialog(uri: String, installations: &mut Vec<Installation>) -> Option<Command> {
-    Some(console::open_dialog(uri, installations)?.create_command())
+pub fn confirm_uri(uri: &str, scheme: &str) -> bool {
+    console::confirm_uri(uri, scheme)
 }
 
 #[cfg(all(feature = "GUI", not(feature = "CLI")))]
 #[must_use]
-pub fn open_dialog(uri: String, installations: &mut Vec<Installation>) -> Option<Command> {
-    Some(gui::open_dialog(uri, installations)?.create_command())
+pub fn confirm_uri(uri: &str, scheme: &str) -> bool {
+    // TODO Ask in the GUI, until then URIs needing confirmation are refused
+    log::warn!(
+        "Can't ask whether to open {} with the scheme `{}` in the GUI yet, refusing it",
+        uri,
+        scheme
+    );
+    false
 }
 
 #[cfg(any(
     all(feature = "CLI", feature = "GUI"),
     all(not(feature = "CLI"), not(feature = "GUI"))
 ))]
-pub fn open_dialog(uri: &str, installations: &mut Vec<Installation>) -> Command {
+pub fn confirm_uri(uri: &str, scheme: &str) -> bool {
     compile_error!("You need to select either GUI or CLI!")
 }
 
 #[cfg(all(feature = "CLI", not(feature = "GUI")))]
-pub fn open_config(installations: &mut Vec<Installation>) {
+pub fn open_config(_installations: &mut [Installation]) {
     todo!()
 }
 
 #[cfg(all(feature = "GUI", not(feature = "CLI")))]
-pub fn open_config(installations: &mut Vec<Installation>) {
+pub fn open_config(_installations: &mut [Installation]) {
     todo!()
 }
 
@@ -40,6 +96,6 @@ pub fn open_config(installations: &mut Vec<Installation>) {
     all(feature = "CLI", feature = "GUI"),
     all(not(feature = "CLI"), not(feature = "GUI"))
 ))]
-pub fn open_config(installations: &mut Vec<Installation>) {
+pub fn open_config(_installations: &mut [Installation]) {
     compile_error!("You need to select either GUI or CLI!")
 }
@@ -0,0 +1,85 @@
+use log::{debug, info};
+use std::path::Path;
+use url::Url;
+
+/// Turns an argument naming an existing file into a `file://` URI
+///
+/// Relative paths are resolved against `working_dir`. Anything that already is a URI, or
+/// doesn't exist on disk, is returned unchanged.
+#[must_use]
+pub fn path_to_uri(arg: &str, working_dir: &Path) -> String {
+    // Drive letters like `C:` parse as single letter schemes, real schemes are longer
+    if Url::parse(arg).is_ok_and(|url| url.scheme().len() > 1) {
+        return arg.to_string();
+    }
+    let path = working_dir.join(arg);
+    if !path.exists() {
+        return arg.to_string();
+    }
+    let path = path.canonicalize().unwrap_or(path);
+    // Canonicalising on Windows adds a verbatim prefix, which has no place in a URI
+    #[cfg(target_os = "windows")]
+    let path = path
+        .to_str()
+        .and_then(|p| p.strip_prefix(r"\\?\"))
+        .map_or(path.clone(), std::path::PathBuf::from);
+    match Url::from_file_path(&path) {
+        Ok(url) => {
+            info!("Opening the file {} as {}", path.display(), url);
+            url.into()
+        }
+        Err(()) => {
+            debug!("Could not turn {} into a URI", path.display());
+            arg.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::path_to_uri;
+    use std::fs;
+
+    #[test]
+    fn existing_files_become_uris() {
+        let dir = std::env::temp_dir()
+            .join(format!("plinks-file-{}", std::process::id()))
+            .join("Ünïcode dir");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a b.pdf"), "").unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let expected = url::Url::from_directory_path(&dir)
+            .unwrap()
+            .join("a%20b.pdf")
+            .unwrap();
+        assert!(expected
+            .as_str()
+            .contains("/%C3%9Cn%C3%AFcode%20dir/a%20b.pdf"));
+        // Relative to the working directory
+        assert_eq!(path_to_uri("a b.pdf", &dir), expected.as_str());
+        assert_eq!(
+            path_to_uri("../Ünïcode dir/a b.pdf", &dir),
+            expected.as_str()
+        );
+        // Absolute paths ignore it
+        assert_eq!(
+            path_to_uri(dir.join("a b.pdf").to_str().unwrap(), "/".as_ref()),
+            expected.as_str()
+        );
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn everything_else_passes_through() {
+        let working_dir = std::env::temp_dir();
+        for arg in [
+            "https://example.com/a b",
+            "mailto:a@b.c",
+            "file:///nonexistent/a.pdf",
+            "nonexistent-plinks-file.pdf",
+            "/nonexistent/a.pdf",
+        ] {
+            assert_eq!(path_to_uri(arg, &working_dir), arg);
+        }
+    }
+}
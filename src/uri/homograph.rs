@@ -0,0 +1,222 @@
+use crate::router::RuleTarget;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use unicode_script::{Script, UnicodeScript};
+use url::Url;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HomographConfig {
+    /// Domains which lookalikes are searched for, like `paypal.com`
+    pub protected_domains: Vec<String>,
+    /// Where URIs with suspicious hosts are opened, instead of routing them or asking
+    pub quarantine: Option<RuleTarget>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum HomographWarning {
+    /// The host is an internationalised domain, which is fine by itself
+    Idn { ascii: String, unicode: String },
+    /// A label of the host mixes scripts, like Latin and Cyrillic letters
+    MixedScript { unicode: String, label: String },
+    /// The host looks like, but isn't, a protected domain
+    Lookalike { host: String, protected: String },
+}
+
+impl HomographWarning {
+    /// Whether the host likely imitates another one
+    #[must_use]
+    pub fn is_suspicious(&self) -> bool {
+        !matches!(self, HomographWarning::Idn { .. })
+    }
+}
+
+impl Display for HomographWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HomographWarning::Idn { ascii, unicode } => {
+                write!(f, "`{ascii}` is the internationalised domain `{unicode}`")
+            }
+            HomographWarning::MixedScript { unicode, label } => write!(
+                f,
+                "WARNING: `{unicode}` mixes scripts in `{label}`, it might imitate another domain"
+            ),
+            HomographWarning::Lookalike { host, protected } => {
+                write!(
+                    f,
+                    "WARNING: `{host}` looks like `{protected}`, but is a different domain"
+                )
+            }
+        }
+    }
+}
+
+impl HomographConfig {
+    /// Looks for internationalised, mixed-script and lookalike hosts
+    #[must_use]
+    pub fn check(&self, uri: &str) -> Vec<HomographWarning> {
+        let Some(ascii) = Url::parse(uri)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+        else {
+            return Vec::new();
+        };
+        let (unicode, _) = idna::domain_to_unicode(&ascii);
+        let mut warnings = Vec::new();
+        if unicode != ascii {
+            warnings.push(HomographWarning::Idn {
+                ascii: ascii.clone(),
+                unicode: unicode.clone(),
+            });
+        }
+        if let Some(label) = unicode.split('.').find(|label| mixes_scripts(label)) {
+            warnings.push(HomographWarning::MixedScript {
+                unicode: unicode.clone(),
+                label: label.to_string(),
+            });
+        }
+        if let Some(protected) = self
+            .protected_domains
+            .iter()
+            .find(|protected| imitates(&unicode, &protected.to_lowercase()))
+        {
+            warnings.push(HomographWarning::Lookalike {
+                host: unicode,
+                protected: protected.clone(),
+            });
+        }
+        warnings
+    }
+}
+
+/// Whether the label uses letters of multiple scripts, besides the combinations common in
+/// Chinese, Japanese and Korean
+fn mixes_scripts(label: &str) -> bool {
+    let mut scripts: Vec<Script> = label
+        .chars()
+        .map(|c| c.script())
+        .filter(|script| !matches!(script, Script::Common | Script::Inherited))
+        .collect();
+    scripts.sort_by_key(|script| script.full_name());
+    scripts.dedup();
+    let allowed_with_latin: &[&[Script]] = &[
+        &[Script::Han, Script::Hiragana, Script::Katakana],
+        &[Script::Han, Script::Bopomofo],
+        &[Script::Han, Script::Hangul],
+    ];
+    scripts.len() > 1
+        && !allowed_with_latin.iter().any(|allowed| {
+            scripts
+                .iter()
+                .all(|script| *script == Script::Latin || allowed.contains(script))
+        })
+}
+
+/// Whether `host` or its parent domain with as many labels as `protected` looks like, but
+/// isn't, `protected`
+fn imitates(host: &str, protected: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    let protected_label_count = protected.split('.').count();
+    if labels.len() < protected_label_count {
+        return false;
+    }
+    let domain = labels[labels.len() - protected_label_count..].join(".");
+    if domain == protected {
+        return false;
+    }
+    skeleton(&domain) == skeleton(protected) || edit_distance(&domain, protected) == 1
+}
+
+/// Maps characters to the Latin letters they are commonly confused with
+///
+/// `i` and `l` fold into the same letter, as their lookalikes imitate either.
+fn skeleton(domain: &str) -> String {
+    let mut skeleton = String::with_capacity(domain.len());
+    for c in domain.chars() {
+        skeleton.push(match c {
+            'а' | 'α' | 'ɑ' => 'a',
+            'с' | 'ϲ' => 'c',
+            'ԁ' => 'd',
+            'е' | 'ε' => 'e',
+            'ɡ' => 'g',
+            'һ' => 'h',
+            // Thin vertical strokes are told apart by neither i nor l, so they share one class
+            'i' | 'l' | '1' | '|' | 'ı' | 'і' | 'ι' | 'ӏ' | 'ǀ' | 'ℓ' => 'l',
+            'ј' => 'j',
+            'κ' | 'к' => 'k',
+            'ո' => 'n',
+            'о' | 'ο' | '0' => 'o',
+            'р' | 'ρ' => 'p',
+            'ԛ' => 'q',
+            'ѕ' => 's',
+            'т' | 'τ' => 't',
+            'υ' => 'u',
+            'ν' | 'ѵ' => 'v',
+            'ԝ' | 'ѡ' => 'w',
+            'х' | 'χ' => 'x',
+            'у' | 'γ' => 'y',
+            c => c,
+        });
+    }
+    skeleton.replace("rn", "m").replace("vv", "w")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            current.push(
+                (previous[j] + usize::from(a_char != *b_char))
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, imitates, mixes_scripts, skeleton};
+
+    #[test]
+    fn skeleton_folds_lookalikes() {
+        assert_eq!(skeleton("аррӏе.com"), skeleton("apple.com"));
+        // Cyrillic і and Greek ι imitate an i as often as an l
+        assert_eq!(skeleton("wіkіpedіa.org"), skeleton("wikipedia.org"));
+        assert_eq!(skeleton("wιkιpedιa.org"), skeleton("wikipedia.org"));
+        assert_eq!(skeleton("paypa1.com"), skeleton("paypal.com"));
+        assert_eq!(skeleton("rnicrosoft.com"), skeleton("microsoft.com"));
+        assert_ne!(skeleton("example.com"), skeleton("examp1e.org"));
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("paypal.com", "paypal.com"), 0);
+        assert_eq!(edit_distance("paypa.com", "paypal.com"), 1);
+        assert_eq!(edit_distance("paypall.com", "paypal.com"), 1);
+        assert_eq!(edit_distance("paypak.com", "paypal.com"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn lookalikes_of_protected_domains() {
+        assert!(imitates("login.wіkipedia.org", "wikipedia.org"));
+        assert!(imitates("paypai.com", "paypal.com"));
+        assert!(!imitates("wikipedia.org", "wikipedia.org"));
+        assert!(!imitates("en.wikipedia.org", "wikipedia.org"));
+        assert!(!imitates("org", "wikipedia.org"));
+    }
+
+    #[test]
+    fn mixed_scripts() {
+        assert!(mixes_scripts("wіkipedia"));
+        assert!(!mixes_scripts("wikipedia"));
+        assert!(!mixes_scripts("пример"));
+        assert!(!mixes_scripts("東京tokyoとうきょう"));
+    }
+}
@@ -0,0 +1,35 @@
+//! Processing of the opened URI itself, independent of where it gets routed to
+
+pub mod file;
+pub mod homograph;
+pub mod policy;
+pub mod rewrite;
+pub mod tracking;
+pub mod unwrap;
+
+use crate::app::AppConfig;
+use crate::uri::rewrite::{rewrite_uri, RewriteStep};
+
+/// A URI after every step that happens before routing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preprocessed {
+    pub uri: String,
+    /// The redirectors the URI was unwrapped from, outermost first
+    pub wrappers: Vec<String>,
+    /// The URI after unwrapping, before rewriting
+    pub unwrapped_uri: String,
+    pub rewrites: Vec<RewriteStep>,
+}
+
+/// Unwraps and then rewrites the URI, as configured
+#[must_use]
+pub fn preprocess(uri: &str, config: &AppConfig) -> Preprocessed {
+    let (unwrapped_uri, wrappers) = config.unwrap.unwrap_uri(uri);
+    let (uri, rewrites) = rewrite_uri(&unwrapped_uri, &config.rewrites);
+    Preprocessed {
+        uri,
+        wrappers,
+        unwrapped_uri,
+        rewrites,
+    }
+}
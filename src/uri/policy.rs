@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use url::Url;
+
+/// What happens to URIs of a scheme before they are routed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemeAction {
+    Allow,
+    Deny,
+    /// Ask before opening it, URIs are refused when there is no terminal to ask in
+    Confirm,
+    /// Normalise the URI, dropping credentials, and refuse `file:` URIs on network shares
+    Sanitise,
+}
+
+impl Display for SchemeAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemeAction::Allow => write!(f, "allow"),
+            SchemeAction::Deny => write!(f, "deny"),
+            SchemeAction::Confirm => write!(f, "confirm"),
+            SchemeAction::Sanitise => write!(f, "sanitise"),
+        }
+    }
+}
+
+fn builtin_action(scheme: &str) -> Option<SchemeAction> {
+    match scheme {
+        "http" | "https" | "file" => Some(SchemeAction::Sanitise),
+        "javascript" | "vbscript" | "data" | "blob" | "view-source" | "about" | "chrome"
+        | "resource" | "moz-extension" | "chrome-extension" | "edge" | "brave" | "vivaldi"
+        | "opera" => Some(SchemeAction::Deny),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SchemePolicy {
+    /// Whether web and file URIs are sanitised and script, data and browser-internal URIs denied
+    pub builtin: bool,
+    /// Per scheme, without the trailing `:`, taking precedence over the built-in actions
+    pub schemes: HashMap<String, SchemeAction>,
+    /// For every other scheme, unless a scheme handler is configured for it
+    ///
+    /// Defaults to `confirm`, so unknown schemes opened without a terminal, like from another
+    /// application, are refused. Configure schemes you use that way explicitly.
+    pub default: SchemeAction,
+}
+
+impl Default for SchemePolicy {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            schemes: HashMap::new(),
+            default: SchemeAction::Confirm,
+        }
+    }
+}
+
+/// Why a URI isn't opened
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum Refusal {
+    Unparseable(String),
+    Denied {
+        scheme: String,
+    },
+    NetworkFile {
+        host: String,
+    },
+    /// The user didn't confirm opening it
+    Declined {
+        scheme: String,
+    },
+}
+
+impl Display for Refusal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Refusal::Unparseable(err) => write!(f, "is no valid URI: {err}"),
+            Refusal::Denied { scheme } => write!(f, "uses the denied scheme `{scheme}`"),
+            Refusal::NetworkFile { host } => {
+                write!(f, "is a file on the network share `{host}`")
+            }
+            Refusal::Declined { scheme } => {
+                write!(f, "uses the scheme `{scheme}`, which was not confirmed")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Contains the URI to open, which might have been sanitised
+    Allowed(String),
+    /// The user has to confirm opening the URI of this scheme first
+    Confirm {
+        uri: String,
+        scheme: String,
+    },
+    Refused(Refusal),
+}
+
+impl SchemePolicy {
+    /// The action for the scheme, schemes with a handler are allowed unless configured otherwise
+    #[must_use]
+    pub fn action(&self, scheme: &str, has_handler: bool) -> SchemeAction {
+        let scheme = scheme.trim_end_matches(':').to_lowercase();
+        if let Some(action) = self
+            .schemes
+            .iter()
+            .find(|(configured, _)| configured.trim_end_matches(':').to_lowercase() == scheme)
+            .map(|(_, action)| *action)
+        {
+            return action;
+        }
+        if has_handler {
+            return SchemeAction::Allow;
+        }
+        self.builtin
+            .then(|| builtin_action(&scheme))
+            .flatten()
+            .unwrap_or(self.default)
+    }
+
+    /// Parses the URI and applies the action of its scheme
+    #[must_use]
+    pub fn validate(&self, uri: &str, has_handler: bool) -> Verdict {
+        let mut url = match Url::parse(uri) {
+            Ok(url) => url,
+            Err(err) => return Verdict::Refused(Refusal::Unparseable(err.to_string())),
+        };
+        let scheme = url.scheme().to_string();
+        match self.action(&scheme, has_handler) {
+            SchemeAction::Allow => Verdict::Allowed(uri.to_string()),
+            SchemeAction::Deny => Verdict::Refused(Refusal::Denied { scheme }),
+            SchemeAction::Confirm => Verdict::Confirm {
+                uri: uri.to_string(),
+                scheme,
+            },
+            SchemeAction::Sanitise => {
+                if scheme == "file" {
+                    if let Some(host) = url.host_str().filter(|host| *host != "localhost") {
+                        return Verdict::Refused(Refusal::NetworkFile {
+                            host: host.to_string(),
+                        });
+                    }
+                    if let Some(host) = unc_host(uri) {
+                        return Verdict::Refused(Refusal::NetworkFile { host });
+                    }
+                }
+                // Credentials in web URIs are mostly used to disguise the real host
+                // Both only fail for URIs without a host, which have no credentials anyway
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                Verdict::Allowed(url.into())
+            }
+        }
+    }
+}
+
+/// The server of a `file:` URI like `file:////server/share`, which has no host but still is a
+/// UNC path on Windows
+///
+/// Checked on the URI as given, as parsing it collapses the extra slashes.
+fn unc_host(uri: &str) -> Option<String> {
+    let (_, rest) = uri.split_once(':')?;
+    let rest = rest.replace('\\', "/");
+    let path = match rest.strip_prefix("//") {
+        // Skip the authority, a non-empty one is a host refused already
+        Some(after_slashes) => after_slashes
+            .find('/')
+            .map_or("", |path_start| &after_slashes[path_start..]),
+        None => rest.as_str(),
+    };
+    let unc_path = path.strip_prefix("//")?;
+    Some(
+        unc_path
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Refusal, SchemeAction, SchemePolicy, Verdict};
+
+    fn refused(refusal: Refusal) -> Verdict {
+        Verdict::Refused(refusal)
+    }
+
+    fn network_file(host: &str) -> Verdict {
+        refused(Refusal::NetworkFile {
+            host: host.to_string(),
+        })
+    }
+
+    #[test]
+    fn builtin_actions() {
+        let policy = SchemePolicy::default();
+        for uri in [
+            "javascript:alert(1)",
+            "data:text/html,<b>x</b>",
+            "JavaScript:void(0)",
+        ] {
+            assert!(matches!(
+                policy.validate(uri, false),
+                Verdict::Refused(Refusal::Denied { .. })
+            ));
+        }
+        assert_eq!(policy.action("chrome", false), SchemeAction::Deny);
+        assert_eq!(
+            policy.validate("chrome://settings", false),
+            refused(Refusal::Denied {
+                scheme: "chrome".to_string()
+            })
+        );
+        assert_eq!(
+            policy.validate("foo:bar", false),
+            Verdict::Confirm {
+                uri: "foo:bar".to_string(),
+                scheme: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            policy.validate("foo:bar", true),
+            Verdict::Allowed("foo:bar".to_string())
+        );
+    }
+
+    #[test]
+    fn sanitises_web_uris() {
+        let policy = SchemePolicy::default();
+        assert_eq!(
+            policy.validate("https://user:pw@host/", false),
+            Verdict::Allowed("https://host/".to_string())
+        );
+        assert_eq!(
+            policy.validate("HTTP://Example.COM/a b", false),
+            Verdict::Allowed("http://example.com/a%20b".to_string())
+        );
+    }
+
+    #[test]
+    fn refuses_files_on_network_shares() {
+        let policy = SchemePolicy::default();
+        assert_eq!(
+            policy.validate("file://server/x", false),
+            network_file("server")
+        );
+        assert_eq!(
+            policy.validate("file:////server/share/x", false),
+            network_file("server")
+        );
+        assert_eq!(
+            policy.validate("file://///server/share/x", false),
+            network_file("server")
+        );
+        assert_eq!(
+            policy.validate(r"file:///\\server\share\x", false),
+            network_file("server")
+        );
+        assert_eq!(
+            policy.validate("file:///home/a.pdf", false),
+            Verdict::Allowed("file:///home/a.pdf".to_string())
+        );
+        assert_eq!(
+            policy.validate("file://localhost/home/a.pdf", false),
+            Verdict::Allowed("file:///home/a.pdf".to_string())
+        );
+    }
+
+    #[test]
+    fn configured_schemes_take_precedence() {
+        let policy = SchemePolicy {
+            schemes: [
+                ("https:".to_string(), SchemeAction::Deny),
+                ("JavaScript".to_string(), SchemeAction::Allow),
+                ("mailto".to_string(), SchemeAction::Confirm),
+            ]
+            .into(),
+            ..SchemePolicy::default()
+        };
+        assert_eq!(policy.action("https", false), SchemeAction::Deny);
+        assert_eq!(policy.action("javascript", false), SchemeAction::Allow);
+        assert_eq!(policy.action("mailto", true), SchemeAction::Confirm);
+        let without_builtin = SchemePolicy {
+            builtin: false,
+            default: SchemeAction::Allow,
+            ..SchemePolicy::default()
+        };
+        assert_eq!(
+            without_builtin.action("javascript", false),
+            SchemeAction::Allow
+        );
+    }
+
+    #[test]
+    fn unparseable_uris_are_refused() {
+        assert!(matches!(
+            SchemePolicy::default().validate("not a uri", false),
+            Verdict::Refused(Refusal::Unparseable(_))
+        ));
+    }
+}
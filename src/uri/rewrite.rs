@@ -0,0 +1,129 @@
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Rewriting stops after this many steps, as rewrites can easily end up looping
+pub const MAX_REWRITES: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Rewrite {
+    pub regex: String,
+    /// Replaces the first match of `regex`, captures are referenced by `$1` or `${name}`
+    pub replacement: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RewriteStep {
+    /// Index of the applied rewrite in the config
+    pub rewrite_index: usize,
+    /// The URI after the rewrite
+    pub uri: String,
+}
+
+/// Applies the first rewrite changing the URI, over and over until none does
+///
+/// Returns the final URI and every step taken. Stops after `MAX_REWRITES` steps.
+#[must_use]
+pub fn rewrite_uri(uri: &str, rewrites: &[Rewrite]) -> (String, Vec<RewriteStep>) {
+    let regexes: Vec<Option<Regex>> = rewrites
+        .iter()
+        .map(|rewrite| {
+            Regex::new(&rewrite.regex)
+                .inspect_err(|err| {
+                    warn!(
+                        "Ignoring rewrite with invalid regex `{}`: {}",
+                        rewrite.regex, err
+                    );
+                })
+                .ok()
+        })
+        .collect();
+    let mut uri = uri.to_string();
+    let mut steps = Vec::new();
+    'rewriting: while steps.len() < MAX_REWRITES {
+        for (rewrite_index, (rewrite, regex)) in rewrites.iter().zip(&regexes).enumerate() {
+            let Some(regex) = regex else {
+                continue;
+            };
+            let rewritten = regex.replace(&uri, rewrite.replacement.as_str());
+            if rewritten != uri {
+                info!(
+                    "Rewrite {} turned {} into {}",
+                    rewrite_index, uri, rewritten
+                );
+                uri = rewritten.into_owned();
+                steps.push(RewriteStep {
+                    rewrite_index,
+                    uri: uri.clone(),
+                });
+                continue 'rewriting;
+            }
+        }
+        return (uri, steps);
+    }
+    warn!(
+        "Stopped after {} rewrites, the rewrites are likely looping",
+        MAX_REWRITES
+    );
+    (uri, steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_uri, Rewrite, RewriteStep, MAX_REWRITES};
+
+    fn rewrite(regex: &str, replacement: &str) -> Rewrite {
+        Rewrite {
+            regex: regex.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn captures_in_replacements() {
+        let rewrites = [
+            rewrite(
+                r"^https?://(?:www\.|mobile\.)?twitter\.com/(?<rest>.*)$",
+                "https://nitter.net/${rest}",
+            ),
+            rewrite(r"^http://(example\.com)/", "https://$1/"),
+        ];
+        assert_eq!(
+            rewrite_uri("https://mobile.twitter.com/user/status/1", &rewrites),
+            (
+                "https://nitter.net/user/status/1".to_string(),
+                vec![RewriteStep {
+                    rewrite_index: 0,
+                    uri: "https://nitter.net/user/status/1".to_string()
+                }]
+            )
+        );
+        assert_eq!(
+            rewrite_uri("http://example.com/a", &rewrites).0,
+            "https://example.com/a"
+        );
+        let unchanged = rewrite_uri("https://other.com/", &rewrites);
+        assert_eq!(unchanged, ("https://other.com/".to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn invalid_regexes_are_skipped() {
+        let rewrites = [rewrite("(", "x"), rewrite("^http:", "https:")];
+        let (uri, steps) = rewrite_uri("http://example.com/", &rewrites);
+        assert_eq!(uri, "https://example.com/");
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].rewrite_index, 1);
+    }
+
+    #[test]
+    fn loops_stop_after_max_rewrites() {
+        let rewrites = [
+            rewrite("^https://a.com/", "https://b.com/"),
+            rewrite("^https://b.com/", "https://a.com/"),
+        ];
+        let (uri, steps) = rewrite_uri("https://a.com/x", &rewrites);
+        assert_eq!(steps.len(), MAX_REWRITES);
+        assert_eq!(uri, "https://a.com/x");
+        assert_eq!(steps.last().unwrap().rewrite_index, 1);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::app::CommandArguments;
+use crate::router::glob_match;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Globs of query parameters which only serve to track where a link was clicked
+const BUILTIN_PARAMS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "igshid",
+    "li_fat_id",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "rb_clickid",
+    "s_cid",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// Whether to strip tracking parameters if neither the rule nor the profile say otherwise
+    pub enabled: bool,
+    /// Globs of parameter names to strip in addition to the built-in ones, like `ref_*`
+    pub params: Vec<String>,
+    /// Overrides `enabled` for single profiles, keyed by the profile path
+    pub profiles: HashMap<PathBuf, bool>,
+}
+
+impl TrackingConfig {
+    /// Whether to strip URIs opened in the profile, `rule_override` being the setting of the
+    /// rule that routed the URI, if any
+    #[must_use]
+    pub fn should_strip(&self, rule_override: Option<bool>, profile_path: &Path) -> bool {
+        rule_override
+            .or_else(|| self.profiles.get(profile_path).copied())
+            .unwrap_or(self.enabled)
+    }
+    /// Strips the URIs of `command_args` if they should be, returning the removed parameters
+    pub fn apply(
+        &self,
+        command_args: &mut CommandArguments,
+        rule_override: Option<bool>,
+    ) -> Vec<String> {
+        if !self.should_strip(rule_override, &command_args.profile.profile_path) {
+            return Vec::new();
+        }
+        let mut all_removed = Vec::new();
+        for uri in &mut command_args.uris {
+            let (stripped, mut removed) = strip_tracking(uri, &self.params);
+            if !removed.is_empty() {
+                info!("Removed tracking parameters {:?}", removed);
+                *uri = stripped;
+                all_removed.append(&mut removed);
+            }
+        }
+        all_removed
+    }
+}
+
+/// Removes every query parameter matching a built-in or one of the `extra_params` globs
+///
+/// Returns the stripped URI and the names of the removed parameters. Kept parameters are left
+/// encoded exactly as they were.
+#[must_use]
+pub fn strip_tracking(uri: &str, extra_params: &[String]) -> (String, Vec<String>) {
+    let mut url = match Url::parse(uri) {
+        Ok(url) if url.query().is_some() => url,
+        Ok(_) => return (uri.to_string(), Vec::new()),
+        Err(err) => {
+            debug!("Not stripping `{}`, as it can't be parsed: {}", uri, err);
+            return (uri.to_string(), Vec::new());
+        }
+    };
+    let mut removed = Vec::new();
+    let kept: Vec<&str> = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let Some((name, _)) = url::form_urlencoded::parse(pair.as_bytes()).next() else {
+                return true;
+            };
+            let is_tracking = BUILTIN_PARAMS
+                .iter()
+                .copied()
+                .chain(extra_params.iter().map(String::as_str))
+                .any(|glob| glob_match(&glob.to_lowercase(), &name));
+            if is_tracking {
+                removed.push(name.into_owned());
+            }
+            !is_tracking
+        })
+        .collect();
+    if removed.is_empty() {
+        return (uri.to_string(), removed);
+    }
+    let kept = kept.join("&");
+    url.set_query((!kept.is_empty()).then_some(kept.as_str()));
+    (url.into(), removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_tracking, TrackingConfig};
+    use std::path::Path;
+
+    #[test]
+    fn strips_builtin_and_extra_params() {
+        let (stripped, removed) = strip_tracking(
+            "https://example.com/a?id=1&utm_source=x&UTM_Medium=y&fbclid=z&ref_src=t#top",
+            &["ref_*".to_string()],
+        );
+        assert_eq!(stripped, "https://example.com/a?id=1#top");
+        assert_eq!(
+            removed,
+            vec!["utm_source", "UTM_Medium", "fbclid", "ref_src"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_rest_as_it_was() {
+        let uri = "https://example.com/?q=a%20b+c&x=%2F";
+        assert_eq!(strip_tracking(uri, &[]), (uri.to_string(), Vec::new()));
+        assert_eq!(
+            strip_tracking("https://example.com/?q=a%20b&gclid=1", &[]).0,
+            "https://example.com/?q=a%20b"
+        );
+        assert_eq!(
+            strip_tracking("https://example.com/path?utm_source=x", &[]).0,
+            "https://example.com/path"
+        );
+        assert_eq!(
+            strip_tracking("not a uri?utm_source=x", &[]).0,
+            "not a uri?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn rule_then_profile_then_enabled() {
+        let config = TrackingConfig {
+            enabled: true,
+            params: Vec::new(),
+            profiles: [(Path::new("/p").to_path_buf(), false)].into(),
+        };
+        assert!(!config.should_strip(None, Path::new("/p")));
+        assert!(config.should_strip(Some(true), Path::new("/p")));
+        assert!(config.should_strip(None, Path::new("/other")));
+        assert!(!config.should_strip(Some(false), Path::new("/other")));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::router::glob_match;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Unwrapping stops after this many layers, nobody nests redirectors deeper than that
+const MAX_DEPTH: usize = 10;
+
+/// Describes a redirector which carries its destination in a query parameter
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Unwrapper {
+    /// Glob over the host of the wrapper
+    pub host: String,
+    pub path_prefix: Option<String>,
+    /// The query parameter holding the destination
+    pub param: String,
+}
+
+impl Unwrapper {
+    fn builtin(host: &str, path_prefix: Option<&str>, param: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            path_prefix: path_prefix.map(str::to_string),
+            param: param.to_string(),
+        }
+    }
+    /// The destination wrapped in `url`, if this unwrapper applies to it
+    #[must_use]
+    pub fn unwrap_url(&self, url: &Url) -> Option<Url> {
+        if !url
+            .host_str()
+            .is_some_and(|host| glob_match(&self.host.to_lowercase(), host))
+        {
+            return None;
+        }
+        if let Some(path_prefix) = &self.path_prefix {
+            if !url.path().starts_with(path_prefix.as_str()) {
+                return None;
+            }
+        }
+        let (_, destination) = url.query_pairs().find(|(name, _)| name == &self.param)?;
+        // Only absolute URLs, the parameter might as well be something relative
+        Url::parse(&destination)
+            .inspect_err(|err| debug!("Wrapped `{}` is no URL: {}", destination, err))
+            .ok()
+    }
+}
+
+fn builtin_unwrappers() -> Vec<Unwrapper> {
+    vec![
+        Unwrapper::builtin("*.safelinks.protection.outlook.com", None, "url"),
+        Unwrapper::builtin(
+            "statics.teams.cdn.office.net",
+            Some("/evergreen-assets/safelinks/"),
+            "url",
+        ),
+        Unwrapper::builtin("google.*", Some("/url"), "q"),
+        Unwrapper::builtin("google.*", Some("/url"), "url"),
+        Unwrapper::builtin("www.google.*", Some("/url"), "q"),
+        Unwrapper::builtin("www.google.*", Some("/url"), "url"),
+        Unwrapper::builtin("slack-redir.net", Some("/link"), "url"),
+        Unwrapper::builtin("l.facebook.com", Some("/l.php"), "u"),
+        Unwrapper::builtin("lm.facebook.com", Some("/l.php"), "u"),
+        Unwrapper::builtin("l.messenger.com", Some("/l.php"), "u"),
+        Unwrapper::builtin("l.instagram.com", None, "u"),
+        Unwrapper::builtin("www.youtube.com", Some("/redirect"), "q"),
+        Unwrapper::builtin("www.linkedin.com", Some("/redir/redirect"), "url"),
+        Unwrapper::builtin("steamcommunity.com", Some("/linkfilter/"), "url"),
+        Unwrapper::builtin("steamcommunity.com", Some("/linkfilter/"), "u"),
+        Unwrapper::builtin("out.reddit.com", None, "url"),
+        Unwrapper::builtin("vk.com", Some("/away.php"), "to"),
+    ]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct UnwrapConfig {
+    /// Whether to use the built-in unwrappers for Outlook Safe Links, Google, Slack, Facebook...
+    pub builtin: bool,
+    /// Tried before the built-in ones
+    pub unwrappers: Vec<Unwrapper>,
+}
+
+impl Default for UnwrapConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            unwrappers: Vec::new(),
+        }
+    }
+}
+
+impl UnwrapConfig {
+    /// Recursively unwraps the URI, without any network access
+    ///
+    /// Returns the innermost URI and every wrapper that was removed, outermost first. URIs which
+    /// aren't wrapped are returned unchanged.
+    #[must_use]
+    pub fn unwrap_uri(&self, uri: &str) -> (String, Vec<String>) {
+        let Ok(mut url) = Url::parse(uri) else {
+            return (uri.to_string(), Vec::new());
+        };
+        let builtin = if self.builtin {
+            builtin_unwrappers()
+        } else {
+            Vec::new()
+        };
+        let mut wrappers = Vec::new();
+        while wrappers.len() < MAX_DEPTH {
+            let Some(destination) = self
+                .unwrappers
+                .iter()
+                .chain(&builtin)
+                .find_map(|unwrapper| unwrapper.unwrap_url(&url))
+            else {
+                break;
+            };
+            info!("Unwrapped {} from {}", destination, url);
+            wrappers.push(std::mem::replace(&mut url, destination).into());
+        }
+        if wrappers.is_empty() {
+            // Return it exactly as given, parsing might have normalised it
+            return (uri.to_string(), wrappers);
+        }
+        (url.into(), wrappers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UnwrapConfig, Unwrapper};
+
+    #[test]
+    fn unwraps_nested_redirectors() {
+        let outlook = "https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fwww.google.com%2Furl%3Fq%3Dhttps%253A%252F%252Fexample.com%252Fa%253Fb%253D1&data=x";
+        let (uri, wrappers) = UnwrapConfig::default().unwrap_uri(outlook);
+        assert_eq!(uri, "https://example.com/a?b=1");
+        assert_eq!(wrappers.len(), 2);
+        assert!(wrappers[0].starts_with("https://eur01.safelinks"));
+        assert!(wrappers[1].starts_with("https://www.google.com/url"));
+    }
+
+    #[test]
+    fn leaves_other_uris_unchanged() {
+        let config = UnwrapConfig::default();
+        for uri in [
+            "https://example.com/?url=https%3A%2F%2Fother.com",
+            "https://www.google.com/search?q=https%3A%2F%2Fexample.com",
+            "https://www.google.com/url?q=relative/path",
+            "HTTPS://Example.com",
+            "mailto:someone@example.com",
+        ] {
+            assert_eq!(config.unwrap_uri(uri), (uri.to_string(), Vec::new()));
+        }
+    }
+
+    #[test]
+    fn custom_unwrappers_without_builtin() {
+        let config = UnwrapConfig {
+            builtin: false,
+            unwrappers: vec![Unwrapper {
+                host: "*.corp.com".to_string(),
+                path_prefix: Some("/go".to_string()),
+                param: "to".to_string(),
+            }],
+        };
+        assert_eq!(
+            config
+                .unwrap_uri("https://links.corp.com/go?to=https%3A%2F%2Fexample.com%2F")
+                .0,
+            "https://example.com/"
+        );
+        let google = "https://www.google.com/url?q=https%3A%2F%2Fexample.com%2F";
+        assert_eq!(config.unwrap_uri(google).0, google);
+    }
+
+    #[test]
+    fn stops_at_max_depth() {
+        let mut uri = "https://example.com/".to_string();
+        for _ in 0..12 {
+            uri = format!(
+                "https://out.reddit.com/?url={}",
+                url::form_urlencoded::byte_serialize(uri.as_bytes()).collect::<String>()
+            );
+        }
+        let (unwrapped, wrappers) = UnwrapConfig::default().unwrap_uri(&uri);
+        assert_eq!(wrappers.len(), super::MAX_DEPTH);
+        assert!(unwrapped.starts_with("https://out.reddit.com/"));
+    }
+}